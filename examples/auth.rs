@@ -5,7 +5,7 @@ use etcd_client::*;
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     println!("connect without user authenticate");
-    let mut client = Client::connect(["localhost:2379"], None).await?;
+    let client = Client::connect(["localhost:2379"], None).await?;
 
     println!("enable authenticate by normal client");
     client.auth_enable().await?;
@@ -16,7 +16,7 @@ async fn main() -> Result<(), Error> {
         "root",    // user name
         "rootpwd", // password
     ));
-    let mut client_auth = Client::connect(["localhost:2379"], options).await?;
+    let client_auth = Client::connect(["localhost:2379"], options).await?;
 
     println!("disable authenticate by authenticated client");
     client_auth.auth_disable().await?;
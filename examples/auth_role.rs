@@ -5,7 +5,7 @@ use etcd_client::*;
 #[allow(unused_must_use)]
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let mut client = Client::connect(["localhost:2379"], None).await?;
+    let client = Client::connect(["localhost:2379"], None).await?;
     let role1 = "role1";
     let role2 = "role2";
 
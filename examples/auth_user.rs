@@ -5,7 +5,7 @@ use etcd_client::*;
 #[allow(unused_must_use)]
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let mut client = Client::connect(["localhost:2379"], None).await?;
+    let client = Client::connect(["localhost:2379"], None).await?;
     let name1 = "usr1";
     let password1 = "pwd1";
     let name2 = "usr2";
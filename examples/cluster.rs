@@ -4,7 +4,7 @@ use etcd_client::*;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let mut client = Client::connect(["localhost:2379"], None).await?;
+    let client = Client::connect(["localhost:2379"], None).await?;
 
     // add a member
     let resp = client.member_add(["localhost:2520"], None).await?;
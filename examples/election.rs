@@ -4,7 +4,7 @@ use etcd_client::*;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let mut client = Client::connect(["localhost:2379"], None).await?;
+    let client = Client::connect(["localhost:2379"], None).await?;
     let resp = client.lease_grant(10, None).await?;
     let lease_id = resp.id();
     println!("grant ttl:{:?}, id:{:?}", resp.ttl(), resp.id());
@@ -1,6 +1,6 @@
 //! Authentication service.
 
-use http::{header::AUTHORIZATION, HeaderValue, Request};
+use http::{header::AUTHORIZATION, HeaderMap, HeaderValue, Request};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use tower_service::Service;
@@ -9,12 +9,22 @@ use tower_service::Service;
 pub struct AuthService<S> {
     inner: S,
     token: Option<Arc<HeaderValue>>,
+    /// default metadata of all requests
+    metadata: Option<Arc<HeaderMap>>,
 }
 
 impl<S> AuthService<S> {
     #[inline]
-    pub fn new(inner: S, token: Option<Arc<HeaderValue>>) -> Self {
-        Self { inner, token }
+    pub fn new(
+        inner: S,
+        token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+    ) -> Self {
+        Self {
+            inner,
+            token,
+            metadata,
+        }
     }
 }
 
@@ -33,6 +43,15 @@ where
 
     #[inline]
     fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        if let Some(metadata) = &self.metadata {
+            let headers = request.headers_mut();
+            for (name, value) in metadata.iter() {
+                // the metadata of the request takes precedence
+                if !headers.contains_key(name) {
+                    headers.insert(name.clone(), value.clone());
+                }
+            }
+        }
         if let Some(token) = &self.token {
             request
                 .headers_mut()
@@ -0,0 +1,865 @@
+//! Synchronous client.
+//!
+//! The blocking [`Client`] wraps the asynchronous [`crate::Client`] and drives it with
+//! its own tokio runtime, so it can be used from code that is not running in an
+//! asynchronous context.
+//!
+//! Notes that the blocking client must not be created or used inside an asynchronous
+//! runtime, as it blocks the current thread while waiting for responses.
+
+use crate::client::ConnectOptions;
+use crate::error::Result;
+use crate::ops::access::RbacEvaluator;
+use crate::ops::alarm::AlarmEvent;
+use crate::ops::consistency::{ConsistencyCheckOptions, ConsistencyReport};
+use crate::ops::defrag::{RollingDefragOptions, RollingDefragReport};
+use crate::ops::leader::TransferLeaderOptions;
+use crate::ops::membership::MembershipOptions;
+use crate::ops::rbac::{RbacConfig, RbacPlan};
+use crate::rpc::auth::{
+    AuthDisableResponse, AuthEnableResponse, Permission, RoleAddResponse, RoleDeleteResponse,
+    RoleGetResponse, RoleGrantPermissionResponse, RoleListResponse, RoleRevokePermissionOptions,
+    RoleRevokePermissionResponse, UserAddOptions, UserAddResponse, UserChangePasswordResponse,
+    UserDeleteResponse, UserGetResponse, UserGrantRoleResponse, UserListResponse,
+    UserRevokeRoleResponse,
+};
+use crate::rpc::cluster::{
+    Member, MemberAddOptions, MemberAddResponse, MemberListResponse, MemberPromoteResponse,
+    MemberRemoveResponse, MemberUpdateResponse,
+};
+use crate::rpc::election::{
+    CampaignResponse, LeaderResponse, ProclaimOptions, ProclaimResponse, ResignOptions,
+    ResignResponse,
+};
+use crate::rpc::kv::{
+    CompactionOptions, CompactionResponse, DeleteOptions, DeleteResponse, GetOptions, GetResponse,
+    PutOptions, PutResponse, Txn, TxnResponse,
+};
+use crate::rpc::lease::{
+    LeaseGrantOptions, LeaseGrantResponse, LeaseKeepAliveResponse, LeaseLeasesResponse,
+    LeaseRevokeResponse, LeaseTimeToLiveOptions, LeaseTimeToLiveResponse,
+};
+use crate::rpc::lock::{LockOptions, LockResponse, UnlockResponse};
+use crate::rpc::maintenance::{
+    AlarmAction, AlarmOptions, AlarmResponse, AlarmType, DefragmentResponse, HashKvResponse,
+    HashResponse, MoveLeaderResponse, SnapshotResponse, StatusResponse,
+};
+use crate::rpc::watch::{WatchOptions, WatchResponse};
+#[cfg(feature = "tls")]
+use crate::TlsOptions;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::{Builder, Runtime};
+
+/// Worker threads of the runtime of a client, which drive its connections and
+/// background tasks between calls.
+const WORKER_THREADS: usize = 2;
+
+/// Builds the runtime owned by a client.
+#[inline]
+fn runtime() -> Result<Runtime> {
+    Ok(Builder::new_multi_thread()
+        .worker_threads(WORKER_THREADS)
+        .enable_all()
+        .build()?)
+}
+
+/// Synchronous `etcd` client using v3 API.
+#[derive(Clone)]
+pub struct Client {
+    inner: crate::Client,
+    rt: Arc<Runtime>,
+}
+
+impl Client {
+    /// Connect to `etcd` servers from given `endpoints`.
+    pub fn connect<E: AsRef<str>, S: AsRef<[E]>>(
+        endpoints: S,
+        options: Option<ConnectOptions>,
+    ) -> Result<Self> {
+        let rt = runtime()?;
+        let inner = rt.block_on(crate::Client::connect(endpoints, options))?;
+        Ok(Self {
+            inner,
+            rt: Arc::new(rt),
+        })
+    }
+
+    /// Connect to `etcd` servers configured by the `ETCDCTL_*` environment variables,
+    /// like etcdctl.
+    pub fn connect_from_env() -> Result<Self> {
+        let rt = runtime()?;
+        let inner = rt.block_on(crate::Client::connect_from_env())?;
+        Ok(Self {
+            inner,
+            rt: Arc::new(rt),
+        })
+    }
+
+    /// Gets the underlying asynchronous client.
+    #[inline]
+    pub fn async_client(&self) -> crate::Client {
+        self.inner.clone()
+    }
+
+    /// Replaces the TLS options, reconnecting to the endpoints with them.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub fn reload_tls(&self, tls: TlsOptions) -> Result<()> {
+        self.rt.block_on(self.inner.reload_tls(tls))
+    }
+
+    /// Replaces the client certificate used for authentication, reconnecting to the endpoints.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub fn reload_client_cert_auth(
+        &self,
+        cert: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> Result<()> {
+        self.rt
+            .block_on(self.inner.reload_client_cert_auth(cert, key))
+    }
+
+    /// Put the given key into the key-value store.
+    /// A put request increments the revision of the key-value store
+    /// and generates one event in the event history.
+    #[inline]
+    pub fn put(
+        &self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+        options: Option<PutOptions>,
+    ) -> Result<PutResponse> {
+        self.rt.block_on(self.inner.put(key, value, options))
+    }
+
+    /// Gets the key from the key-value store.
+    #[inline]
+    pub fn get(&self, key: impl Into<Vec<u8>>, options: Option<GetOptions>) -> Result<GetResponse> {
+        self.rt.block_on(self.inner.get(key, options))
+    }
+
+    /// Deletes the given key from the key-value store.
+    #[inline]
+    pub fn delete(
+        &self,
+        key: impl Into<Vec<u8>>,
+        options: Option<DeleteOptions>,
+    ) -> Result<DeleteResponse> {
+        self.rt.block_on(self.inner.delete(key, options))
+    }
+
+    /// Compacts the event history in the etcd key-value store. The key-value
+    /// store should be periodically compacted or the event history will continue to grow
+    /// indefinitely.
+    #[inline]
+    pub fn compact(
+        &self,
+        revision: i64,
+        options: Option<CompactionOptions>,
+    ) -> Result<CompactionResponse> {
+        self.rt.block_on(self.inner.compact(revision, options))
+    }
+
+    /// Processes multiple operations in a single transaction.
+    /// A txn request increments the revision of the key-value store
+    /// and generates events with the same revision for every completed operation.
+    /// It is not allowed to modify the same key several times within one txn.
+    #[inline]
+    pub fn txn(&self, txn: Txn) -> Result<TxnResponse> {
+        self.rt.block_on(self.inner.txn(txn))
+    }
+
+    /// Watches for events happening or that have happened. Both input and output
+    /// are streams; the input stream is for creating and canceling watcher and the output
+    /// stream sends events. The entire event history can be watched starting from the
+    /// last compaction revision.
+    #[inline]
+    pub fn watch(
+        &self,
+        key: impl Into<Vec<u8>>,
+        options: Option<WatchOptions>,
+    ) -> Result<(Watcher, WatchStream)> {
+        let (watcher, stream) = self.rt.block_on(self.inner.watch(key, options))?;
+        Ok((
+            Watcher {
+                inner: watcher,
+                rt: self.rt.clone(),
+            },
+            WatchStream {
+                inner: stream,
+                rt: self.rt.clone(),
+            },
+        ))
+    }
+
+    /// Creates a lease which expires if the server does not receive a keepAlive
+    /// within a given time to live period. All keys attached to the lease will be expired and
+    /// deleted if the lease expires. Each expired key generates a delete event in the event history.
+    #[inline]
+    pub fn lease_grant(
+        &self,
+        ttl: i64,
+        options: Option<LeaseGrantOptions>,
+    ) -> Result<LeaseGrantResponse> {
+        self.rt.block_on(self.inner.lease_grant(ttl, options))
+    }
+
+    /// Revokes a lease. All keys attached to the lease will expire and be deleted.
+    #[inline]
+    pub fn lease_revoke(&self, id: i64) -> Result<LeaseRevokeResponse> {
+        self.rt.block_on(self.inner.lease_revoke(id))
+    }
+
+    /// Keeps the lease alive by streaming keep alive requests from the client
+    /// to the server and streaming keep alive responses from the server to the client.
+    #[inline]
+    pub fn lease_keep_alive(&self, id: i64) -> Result<(LeaseKeeper, LeaseKeepAliveStream)> {
+        let (keeper, stream) = self.rt.block_on(self.inner.lease_keep_alive(id))?;
+        Ok((
+            LeaseKeeper {
+                inner: keeper,
+                rt: self.rt.clone(),
+            },
+            LeaseKeepAliveStream {
+                inner: stream,
+                rt: self.rt.clone(),
+            },
+        ))
+    }
+
+    /// Retrieves lease information.
+    #[inline]
+    pub fn lease_time_to_live(
+        &self,
+        id: i64,
+        options: Option<LeaseTimeToLiveOptions>,
+    ) -> Result<LeaseTimeToLiveResponse> {
+        self.rt.block_on(self.inner.lease_time_to_live(id, options))
+    }
+
+    /// Lists all existing leases.
+    #[inline]
+    pub fn leases(&self) -> Result<LeaseLeasesResponse> {
+        self.rt.block_on(self.inner.leases())
+    }
+
+    /// Lock acquires a distributed shared lock on a given named lock.
+    /// On success, it will return a unique key that exists so long as the
+    /// lock is held by the caller. This key can be used in conjunction with
+    /// transactions to safely ensure updates to etcd only occur while holding
+    /// lock ownership. The lock is held until Unlock is called on the key or the
+    /// lease associate with the owner expires.
+    #[inline]
+    pub fn lock(
+        &self,
+        name: impl Into<Vec<u8>>,
+        options: Option<LockOptions>,
+    ) -> Result<LockResponse> {
+        self.rt.block_on(self.inner.lock(name, options))
+    }
+
+    /// Unlock takes a key returned by Lock and releases the hold on lock. The
+    /// next Lock caller waiting for the lock will then be woken up and given
+    /// ownership of the lock.
+    #[inline]
+    pub fn unlock(&self, key: impl Into<Vec<u8>>) -> Result<UnlockResponse> {
+        self.rt.block_on(self.inner.unlock(key))
+    }
+
+    /// Enables authentication.
+    #[inline]
+    pub fn auth_enable(&self) -> Result<AuthEnableResponse> {
+        self.rt.block_on(self.inner.auth_enable())
+    }
+
+    /// Disables authentication.
+    #[inline]
+    pub fn auth_disable(&self) -> Result<AuthDisableResponse> {
+        self.rt.block_on(self.inner.auth_disable())
+    }
+
+    /// Adds role.
+    #[inline]
+    pub fn role_add(&self, name: impl Into<String>) -> Result<RoleAddResponse> {
+        self.rt.block_on(self.inner.role_add(name))
+    }
+
+    /// Deletes role.
+    #[inline]
+    pub fn role_delete(&self, name: impl Into<String>) -> Result<RoleDeleteResponse> {
+        self.rt.block_on(self.inner.role_delete(name))
+    }
+
+    /// Gets role.
+    #[inline]
+    pub fn role_get(&self, name: impl Into<String>) -> Result<RoleGetResponse> {
+        self.rt.block_on(self.inner.role_get(name))
+    }
+
+    /// Lists role.
+    #[inline]
+    pub fn role_list(&self) -> Result<RoleListResponse> {
+        self.rt.block_on(self.inner.role_list())
+    }
+
+    /// Grants role permission.
+    #[inline]
+    pub fn role_grant_permission(
+        &self,
+        name: impl Into<String>,
+        perm: Permission,
+    ) -> Result<RoleGrantPermissionResponse> {
+        self.rt
+            .block_on(self.inner.role_grant_permission(name, perm))
+    }
+
+    /// Revokes role permission.
+    #[inline]
+    pub fn role_revoke_permission(
+        &self,
+        name: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        options: Option<RoleRevokePermissionOptions>,
+    ) -> Result<RoleRevokePermissionResponse> {
+        self.rt
+            .block_on(self.inner.role_revoke_permission(name, key, options))
+    }
+
+    /// Computes the changes bringing the users and roles of the cluster to `config`.
+    #[inline]
+    pub fn plan_rbac(&self, config: &RbacConfig) -> Result<RbacPlan> {
+        self.rt.block_on(self.inner.plan_rbac(config))
+    }
+
+    /// Applies the changes of a plan computed by [`Client::plan_rbac`].
+    #[inline]
+    pub fn apply_rbac(&self, plan: &RbacPlan) -> Result<()> {
+        self.rt.block_on(self.inner.apply_rbac(plan))
+    }
+
+    /// Fetches all users and roles to answer access queries and audit grants offline.
+    #[inline]
+    pub fn rbac_evaluator(&self) -> Result<RbacEvaluator> {
+        self.rt.block_on(self.inner.rbac_evaluator())
+    }
+
+    /// Add an user.
+    #[inline]
+    pub fn user_add(
+        &self,
+        name: impl Into<String>,
+        password: impl Into<String>,
+        options: Option<UserAddOptions>,
+    ) -> Result<UserAddResponse> {
+        self.rt
+            .block_on(self.inner.user_add(name, password, options))
+    }
+
+    /// Gets the user info by the user name.
+    #[inline]
+    pub fn user_get(&self, name: impl Into<String>) -> Result<UserGetResponse> {
+        self.rt.block_on(self.inner.user_get(name))
+    }
+
+    /// Lists all users.
+    #[inline]
+    pub fn user_list(&self) -> Result<UserListResponse> {
+        self.rt.block_on(self.inner.user_list())
+    }
+
+    /// Deletes the given user.
+    #[inline]
+    pub fn user_delete(&self, name: impl Into<String>) -> Result<UserDeleteResponse> {
+        self.rt.block_on(self.inner.user_delete(name))
+    }
+
+    /// Change password for an user.
+    #[inline]
+    pub fn user_change_password(
+        &self,
+        name: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<UserChangePasswordResponse> {
+        self.rt
+            .block_on(self.inner.user_change_password(name, password))
+    }
+
+    /// Grant role for an user.
+    #[inline]
+    pub fn user_grant_role(
+        &self,
+        user: impl Into<String>,
+        role: impl Into<String>,
+    ) -> Result<UserGrantRoleResponse> {
+        self.rt.block_on(self.inner.user_grant_role(user, role))
+    }
+
+    /// Revoke role for an user.
+    #[inline]
+    pub fn user_revoke_role(
+        &self,
+        user: impl Into<String>,
+        role: impl Into<String>,
+    ) -> Result<UserRevokeRoleResponse> {
+        self.rt.block_on(self.inner.user_revoke_role(user, role))
+    }
+
+    /// Maintain(get, active or inactive) alarms of members.
+    #[inline]
+    pub fn alarm(
+        &self,
+        alarm_action: AlarmAction,
+        alarm_type: AlarmType,
+        options: Option<AlarmOptions>,
+    ) -> Result<AlarmResponse> {
+        self.rt
+            .block_on(self.inner.alarm(alarm_action, alarm_type, options))
+    }
+
+    /// Monitors the alarms of the cluster, polling them every `period`
+    /// and yielding raised and cleared alarms as they change.
+    #[inline]
+    pub fn alarm_monitor(&self, period: Duration) -> AlarmMonitor {
+        AlarmMonitor {
+            inner: self.inner.alarm_monitor(period),
+            rt: self.rt.clone(),
+        }
+    }
+
+    /// Recovers from a `NOSPACE` alarm: compacts to the current revision,
+    /// defragments every member, and disarms the `NOSPACE` alarms.
+    #[inline]
+    pub fn remediate_nospace(&self) -> Result<RollingDefragReport> {
+        self.rt.block_on(self.inner.remediate_nospace())
+    }
+
+    /// Gets the status of a member.
+    #[inline]
+    pub fn status(&self) -> Result<StatusResponse> {
+        self.rt.block_on(self.inner.status())
+    }
+
+    /// Defragments a member's backend database to recover storage space.
+    #[inline]
+    pub fn defragment(&self) -> Result<DefragmentResponse> {
+        self.rt.block_on(self.inner.defragment())
+    }
+
+    /// Gets the status of every member in the cluster, keyed by member ID.
+    #[inline]
+    pub fn cluster_status(&self) -> Result<HashMap<u64, Result<StatusResponse>>> {
+        self.rt.block_on(self.inner.cluster_status())
+    }
+
+    /// Defragments every member in the cluster one at a time, keyed by member ID.
+    #[inline]
+    pub fn cluster_defragment(&self) -> Result<HashMap<u64, Result<DefragmentResponse>>> {
+        self.rt.block_on(self.inner.cluster_defragment())
+    }
+
+    /// Defragments members one at a time, followers first and the leader last,
+    /// waiting for each member to become healthy before moving on.
+    #[inline]
+    pub fn rolling_defragment(
+        &self,
+        options: Option<RollingDefragOptions>,
+    ) -> Result<RollingDefragReport> {
+        self.rt.block_on(self.inner.rolling_defragment(options))
+    }
+
+    /// Checks that every member has the same keyspace by comparing their `HashKV`
+    /// at the same revision.
+    #[inline]
+    pub fn check_consistency(
+        &self,
+        options: Option<ConsistencyCheckOptions>,
+    ) -> Result<ConsistencyReport> {
+        self.rt.block_on(self.inner.check_consistency(options))
+    }
+
+    /// Computes the hash of whole backend keyspace.
+    /// including key, lease, and other buckets in storage.
+    /// This is designed for testing ONLY!
+    #[inline]
+    pub fn hash(&self) -> Result<HashResponse> {
+        self.rt.block_on(self.inner.hash())
+    }
+
+    /// Computes the hash of all MVCC keys up to a given revision.
+    /// It only iterates \"key\" bucket in backend storage.
+    #[inline]
+    pub fn hash_kv(&self, revision: i64) -> Result<HashKvResponse> {
+        self.rt.block_on(self.inner.hash_kv(revision))
+    }
+
+    /// Gets a snapshot of the entire backend from a member over a stream to a client.
+    #[inline]
+    pub fn snapshot(&self) -> Result<SnapshotStreaming> {
+        let stream = self.rt.block_on(self.inner.snapshot())?;
+        Ok(SnapshotStreaming {
+            inner: stream,
+            rt: self.rt.clone(),
+        })
+    }
+
+    /// Saves a snapshot of the entire backend to the file at `path`, verifying its integrity.
+    /// `progress` is called with the number of bytes received so far and the remaining bytes.
+    #[inline]
+    pub fn save_snapshot<F>(&self, path: impl AsRef<Path>, progress: F) -> Result<u64>
+    where
+        F: FnMut(u64, u64),
+    {
+        self.rt.block_on(self.inner.save_snapshot(path, progress))
+    }
+
+    /// Adds current connected server as a member.
+    #[inline]
+    pub fn member_add<E: AsRef<str>, S: AsRef<[E]>>(
+        &self,
+        urls: S,
+        options: Option<MemberAddOptions>,
+    ) -> Result<MemberAddResponse> {
+        self.rt.block_on(self.inner.member_add(urls, options))
+    }
+
+    /// Remove a member.
+    #[inline]
+    pub fn member_remove(&self, id: u64) -> Result<MemberRemoveResponse> {
+        self.rt.block_on(self.inner.member_remove(id))
+    }
+
+    /// Updates the member.
+    #[inline]
+    pub fn member_update(
+        &self,
+        id: u64,
+        url: impl Into<Vec<String>>,
+    ) -> Result<MemberUpdateResponse> {
+        self.rt.block_on(self.inner.member_update(id, url))
+    }
+
+    /// Promotes the member.
+    #[inline]
+    pub fn member_promote(&self, id: u64) -> Result<MemberPromoteResponse> {
+        self.rt.block_on(self.inner.member_promote(id))
+    }
+
+    /// Adds a member as a learner, waits until it has started and caught up with
+    /// the leader, then promotes it to a voting member.
+    #[inline]
+    pub fn add_member<E: AsRef<str>, S: AsRef<[E]>>(
+        &self,
+        peer_urls: S,
+        options: Option<MembershipOptions>,
+    ) -> Result<Member> {
+        self.rt.block_on(self.inner.add_member(peer_urls, options))
+    }
+
+    /// Replaces the member `old_id` by a new member with `peer_urls`,
+    /// adding the new member before removing the old one.
+    #[inline]
+    pub fn replace_member<E: AsRef<str>, S: AsRef<[E]>>(
+        &self,
+        old_id: u64,
+        peer_urls: S,
+        options: Option<MembershipOptions>,
+    ) -> Result<Member> {
+        self.rt
+            .block_on(self.inner.replace_member(old_id, peer_urls, options))
+    }
+
+    /// Removes a member, refusing to if the remaining healthy voting members
+    /// would not form a quorum.
+    #[inline]
+    pub fn remove_member(&self, id: u64) -> Result<()> {
+        self.rt.block_on(self.inner.remove_member(id))
+    }
+
+    /// Lists members.
+    #[inline]
+    pub fn member_list(&self) -> Result<MemberListResponse> {
+        self.rt.block_on(self.inner.member_list())
+    }
+
+    /// Moves the current leader node to target node.
+    #[inline]
+    pub fn move_leader(&self, target_id: u64) -> Result<MoveLeaderResponse> {
+        self.rt.block_on(self.inner.move_leader(target_id))
+    }
+
+    /// Transfers the leadership to the most up-to-date voting member, or to the member
+    /// given in `options`, and waits until the new leader is confirmed.
+    #[inline]
+    pub fn transfer_leader(&self, options: Option<TransferLeaderOptions>) -> Result<u64> {
+        self.rt.block_on(self.inner.transfer_leader(options))
+    }
+
+    /// Puts a value as eligible for the election on the prefix key.
+    /// Multiple sessions can participate in the election for the
+    /// same prefix, but only one can be the leader at a time.
+    #[inline]
+    pub fn campaign(
+        &self,
+        name: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+        lease: i64,
+    ) -> Result<CampaignResponse> {
+        self.rt.block_on(self.inner.campaign(name, value, lease))
+    }
+
+    /// Lets the leader announce a new value without another election.
+    #[inline]
+    pub fn proclaim(
+        &self,
+        value: impl Into<Vec<u8>>,
+        options: Option<ProclaimOptions>,
+    ) -> Result<ProclaimResponse> {
+        self.rt.block_on(self.inner.proclaim(value, options))
+    }
+
+    /// Returns the leader value for the current election.
+    #[inline]
+    pub fn leader(&self, name: impl Into<Vec<u8>>) -> Result<LeaderResponse> {
+        self.rt.block_on(self.inner.leader(name))
+    }
+
+    /// Returns a blocking iterator that reliably observes ordered leader proposals
+    /// as GetResponse values on every current elected leader key.
+    #[inline]
+    pub fn observe(&self, name: impl Into<Vec<u8>>) -> Result<ObserveStream> {
+        let stream = self.rt.block_on(self.inner.observe(name))?;
+        Ok(ObserveStream {
+            inner: stream,
+            rt: self.rt.clone(),
+        })
+    }
+
+    /// Releases election leadership and then start a new election
+    #[inline]
+    pub fn resign(&self, option: Option<ResignOptions>) -> Result<ResignResponse> {
+        self.rt.block_on(self.inner.resign(option))
+    }
+}
+
+/// The blocking watching handle.
+pub struct Watcher {
+    inner: crate::Watcher,
+    rt: Arc<Runtime>,
+}
+
+impl Watcher {
+    /// The ID of the watcher.
+    #[inline]
+    pub const fn watch_id(&self) -> i64 {
+        self.inner.watch_id()
+    }
+
+    /// Cancels this watcher.
+    #[inline]
+    pub fn cancel(&mut self) -> Result<()> {
+        self.rt.block_on(self.inner.cancel())
+    }
+
+    /// Requests a watch stream progress status be sent in the watch response stream as soon as
+    /// possible.
+    #[inline]
+    pub fn request_progress(&mut self) -> Result<()> {
+        self.rt.block_on(self.inner.request_progress())
+    }
+}
+
+/// The blocking watch response stream.
+pub struct WatchStream {
+    inner: crate::WatchStream,
+    rt: Arc<Runtime>,
+}
+
+impl WatchStream {
+    /// Fetches the next message from this stream, blocking until it is available.
+    #[inline]
+    pub fn message(&mut self) -> Result<Option<WatchResponse>> {
+        self.rt.block_on(self.inner.message())
+    }
+}
+
+impl Iterator for WatchStream {
+    type Item = Result<WatchResponse>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.message().transpose()
+    }
+}
+
+/// The blocking lease keep alive handle.
+pub struct LeaseKeeper {
+    inner: crate::LeaseKeeper,
+    rt: Arc<Runtime>,
+}
+
+impl LeaseKeeper {
+    /// The lease id which user want to keep alive.
+    #[inline]
+    pub const fn id(&self) -> i64 {
+        self.inner.id()
+    }
+
+    /// Sends a keep alive request and receive response
+    #[inline]
+    pub fn keep_alive(&mut self) -> Result<()> {
+        self.rt.block_on(self.inner.keep_alive())
+    }
+}
+
+/// The blocking lease keep alive response stream.
+pub struct LeaseKeepAliveStream {
+    inner: crate::LeaseKeepAliveStream,
+    rt: Arc<Runtime>,
+}
+
+impl LeaseKeepAliveStream {
+    /// Fetches the next message from this stream, blocking until it is available.
+    #[inline]
+    pub fn message(&mut self) -> Result<Option<LeaseKeepAliveResponse>> {
+        self.rt.block_on(self.inner.message())
+    }
+}
+
+impl Iterator for LeaseKeepAliveStream {
+    type Item = Result<LeaseKeepAliveResponse>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.message().transpose()
+    }
+}
+
+/// The blocking response stream for `Observe` operation.
+pub struct ObserveStream {
+    inner: crate::ObserveStream,
+    rt: Arc<Runtime>,
+}
+
+impl ObserveStream {
+    /// Fetches the next message from this stream, blocking until it is available.
+    #[inline]
+    pub fn message(&mut self) -> Result<Option<LeaderResponse>> {
+        self.rt.block_on(self.inner.message())
+    }
+}
+
+impl Iterator for ObserveStream {
+    type Item = Result<LeaderResponse>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.message().transpose()
+    }
+}
+
+/// The blocking response stream for `snapshot` operation.
+pub struct SnapshotStreaming {
+    inner: crate::SnapshotStreaming,
+    rt: Arc<Runtime>,
+}
+
+impl SnapshotStreaming {
+    /// Fetches the next message from this stream, blocking until it is available.
+    #[inline]
+    pub fn message(&mut self) -> Result<Option<SnapshotResponse>> {
+        self.rt.block_on(self.inner.message())
+    }
+}
+
+impl Iterator for SnapshotStreaming {
+    type Item = Result<SnapshotResponse>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.message().transpose()
+    }
+}
+
+/// The blocking stream of alarm transitions.
+pub struct AlarmMonitor {
+    inner: crate::AlarmMonitor,
+    rt: Arc<Runtime>,
+}
+
+impl AlarmMonitor {
+    /// Fetches the next alarm transition, blocking until it is available.
+    #[inline]
+    pub fn message(&mut self) -> Result<Option<AlarmEvent>> {
+        self.rt.block_on(self.inner.message())
+    }
+}
+
+impl Iterator for AlarmMonitor {
+    type Item = Result<AlarmEvent>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.message().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::TEST_ENDPOINTS;
+    use crate::EventType;
+
+    #[test]
+    fn test_put_get() -> Result<()> {
+        let client = Client::connect(TEST_ENDPOINTS, None)?;
+        client.put("blocking-put", "123", None)?;
+
+        let resp = client.get("blocking-put", None)?;
+        assert_eq!(resp.kvs().len(), 1);
+        assert_eq!(resp.kvs()[0].value(), b"123");
+
+        client.delete("blocking-put", None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch() -> Result<()> {
+        let client = Client::connect(TEST_ENDPOINTS, None)?;
+
+        let (mut watcher, mut stream) = client.watch("blocking-watch", None)?;
+        client.put("blocking-watch", "01", None)?;
+
+        let resp = stream.next().unwrap()?;
+        assert_eq!(resp.watch_id(), watcher.watch_id());
+        assert_eq!(resp.events().len(), 1);
+        assert_eq!(resp.events()[0].event_type(), EventType::Put);
+
+        watcher.cancel()?;
+
+        let resp = stream.next().unwrap()?;
+        assert!(resp.canceled());
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_alive() -> Result<()> {
+        let client = Client::connect(TEST_ENDPOINTS, None)?;
+
+        let id = client.lease_grant(60, None)?.id();
+        let (mut keeper, mut stream) = client.lease_keep_alive(id)?;
+        keeper.keep_alive()?;
+
+        let resp = stream.next().unwrap()?;
+        assert_eq!(resp.id(), keeper.id());
+        assert_eq!(resp.ttl(), 60);
+
+        client.lease_revoke(id)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+//! gRPC message compression.
+
+/// The gRPC services of etcd, to configure them separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RpcService {
+    /// The KV service.
+    Kv,
+    /// The Watch service.
+    Watch,
+    /// The Lease service.
+    Lease,
+    /// The Lock service.
+    Lock,
+    /// The Auth service.
+    Auth,
+    /// The Maintenance service.
+    Maintenance,
+    /// The Cluster service.
+    Cluster,
+    /// The Election service.
+    Election,
+}
+
+/// Options for gRPC message compression.
+///
+/// Notes that the server has to support `gzip` for compressed requests, otherwise
+/// it responds with an error.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionOptions {
+    send_gzip: bool,
+    accept_gzip: bool,
+}
+
+impl CompressionOptions {
+    /// Creates a `CompressionOptions`, without compression.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            send_gzip: false,
+            accept_gzip: false,
+        }
+    }
+
+    /// Compresses requests with `gzip`.
+    #[inline]
+    pub const fn with_send_gzip(mut self) -> Self {
+        self.send_gzip = true;
+        self
+    }
+
+    /// Accepts responses compressed with `gzip`.
+    #[inline]
+    pub const fn with_accept_gzip(mut self) -> Self {
+        self.accept_gzip = true;
+        self
+    }
+
+    /// Whether requests are compressed with `gzip`.
+    #[inline]
+    pub const fn send_gzip(&self) -> bool {
+        self.send_gzip
+    }
+
+    /// Whether responses compressed with `gzip` are accepted.
+    #[inline]
+    pub const fn accept_gzip(&self) -> bool {
+        self.accept_gzip
+    }
+}
@@ -0,0 +1,249 @@
+//! DNS SRV discovery of endpoints.
+
+use crate::client::ConnectOptions;
+use crate::endpoints::EndpointPool;
+use crate::error::{Error, Result};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Discovers the endpoints from DNS SRV records, like `--discovery-srv` of etcd.
+///
+/// The records `_etcd-client-ssl._tcp.<domain>` give HTTPS endpoints, and the records
+/// `_etcd-client._tcp.<domain>` give HTTP endpoints. When TLS options are set, only the
+/// HTTPS endpoints are used, as etcd does, and without the feature `tls` only the HTTP ones. The records are resolved again periodically,
+/// adding and removing endpoints of the client as they change.
+#[derive(Debug, Clone)]
+pub struct SrvDiscovery {
+    domain: String,
+    service_name: Option<String>,
+    refresh_interval: Duration,
+}
+
+impl SrvDiscovery {
+    /// Creates a `SrvDiscovery` for the records of `domain`.
+    #[inline]
+    pub fn new(domain: impl Into<String>) -> Self {
+        Self {
+            domain: domain.into(),
+            service_name: None,
+            refresh_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// Queries `_etcd-client-ssl-<name>._tcp.<domain>` and `_etcd-client-<name>._tcp.<domain>`
+    /// instead, like `--discovery-srv-name` of etcd.
+    #[inline]
+    pub fn with_service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = Some(name.into());
+        self
+    }
+
+    /// Interval between resolutions of the records after connecting. Defaults to 30s.
+    #[inline]
+    pub const fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// The record name to query, for HTTPS or HTTP endpoints.
+    fn record_name(&self, ssl: bool) -> String {
+        let service = if ssl {
+            "etcd-client-ssl"
+        } else {
+            "etcd-client"
+        };
+        match &self.service_name {
+            Some(name) => format!("_{}-{}._tcp.{}", service, name, self.domain),
+            None => format!("_{}._tcp.{}", service, self.domain),
+        }
+    }
+
+    /// Resolves the records into endpoint URLs.
+    pub async fn resolve(&self) -> Result<Vec<String>> {
+        let resolver = resolver()?;
+        self.resolve_records(&[true, false], |name| lookup(&resolver, name))
+            .await
+    }
+
+    /// Resolves the records of the endpoints that can be built with `options`.
+    pub(crate) async fn resolve_for(
+        &self,
+        options: Option<&ConnectOptions>,
+    ) -> Result<Vec<String>> {
+        let resolver = resolver()?;
+        self.resolve_records_for(options, |name| lookup(&resolver, name))
+            .await
+    }
+
+    /// Resolves the records of the endpoints that can be built with `options`
+    /// with `lookup`.
+    #[inline]
+    async fn resolve_records_for<F, Fut>(
+        &self,
+        options: Option<&ConnectOptions>,
+        lookup: F,
+    ) -> Result<Vec<String>>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<Vec<(String, u16)>>>,
+    {
+        self.resolve_records(usable_records(options), lookup).await
+    }
+
+    /// Resolves the records for HTTPS or HTTP endpoints into endpoint URLs,
+    /// looking up the targets of a record name with `lookup`.
+    async fn resolve_records<F, Fut>(&self, ssl: &[bool], lookup: F) -> Result<Vec<String>>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<Vec<(String, u16)>>>,
+    {
+        let mut endpoints = Vec::new();
+        for &ssl in ssl {
+            let scheme = if ssl { "https" } else { "http" };
+            for (target, port) in lookup(self.record_name(ssl)).await? {
+                endpoints.push(format!(
+                    "{}://{}:{}",
+                    scheme,
+                    target.trim_end_matches('.'),
+                    port
+                ));
+            }
+        }
+
+        if endpoints.is_empty() {
+            return Err(Error::DiscoveryError(format!(
+                "no etcd-client SRV records found for {}",
+                self.domain
+            )));
+        }
+        endpoints.sort();
+        endpoints.dedup();
+        Ok(endpoints)
+    }
+
+    /// Resolves the records periodically into the endpoints of `pool`,
+    /// until its channel is dropped.
+    pub(crate) async fn refresh(self, pool: Arc<EndpointPool>) {
+        loop {
+            sleep(self.refresh_interval).await;
+            if pool.is_closed() {
+                return;
+            }
+            // keep the endpoints on resolution errors
+            let options = pool.options().await;
+            let resolved = match self.resolve_for(options.as_ref()).await {
+                Ok(endpoints) => endpoints.into_iter().collect(),
+                Err(_) => continue,
+            };
+            if pool.update(resolved).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Creates a resolver from the system configuration.
+#[inline]
+fn resolver() -> Result<TokioAsyncResolver> {
+    TokioAsyncResolver::tokio_from_system_conf().map_err(|e| Error::DiscoveryError(e.to_string()))
+}
+
+/// Looks up the targets and ports of the SRV records `name`, none if it has no records.
+async fn lookup(resolver: &TokioAsyncResolver, name: String) -> Result<Vec<(String, u16)>> {
+    match resolver.srv_lookup(name.as_str()).await {
+        Ok(lookup) => Ok(lookup
+            .iter()
+            .map(|srv| (srv.target().to_utf8(), srv.port()))
+            .collect()),
+        Err(e) => match e.kind() {
+            ResolveErrorKind::NoRecordsFound { .. } => Ok(Vec::new()),
+            _ => Err(Error::DiscoveryError(format!(
+                "cannot resolve {}: {}",
+                name, e
+            ))),
+        },
+    }
+}
+
+/// The records, for HTTPS or HTTP endpoints, that can be built with `options`:
+/// HTTPS endpoints need the feature `tls`, and HTTP ones cannot use TLS options.
+#[inline]
+fn usable_records(options: Option<&ConnectOptions>) -> &'static [bool] {
+    #[cfg(feature = "tls")]
+    if options.and_then(ConnectOptions::tls_config).is_some() {
+        return &[true];
+    }
+    #[cfg(feature = "tls")]
+    return &[true, false];
+
+    #[cfg(not(feature = "tls"))]
+    {
+        let _ = options;
+        &[false]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_name() {
+        let discovery = SrvDiscovery::new("example.com");
+        assert_eq!(
+            discovery.record_name(true),
+            "_etcd-client-ssl._tcp.example.com"
+        );
+        assert_eq!(
+            discovery.record_name(false),
+            "_etcd-client._tcp.example.com"
+        );
+
+        let discovery = discovery.with_service_name("prod");
+        assert_eq!(
+            discovery.record_name(true),
+            "_etcd-client-ssl-prod._tcp.example.com"
+        );
+        assert_eq!(
+            discovery.record_name(false),
+            "_etcd-client-prod._tcp.example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_usable_records() -> Result<()> {
+        let discovery = SrvDiscovery::new("example.com");
+        let lookup = |name: String| async move {
+            Ok(match name.as_str() {
+                "_etcd-client-ssl._tcp.example.com" => vec![("a.example.com.".to_string(), 2379)],
+                "_etcd-client._tcp.example.com" => vec![("b.example.com.".to_string(), 2379)],
+                _ => Vec::new(),
+            })
+        };
+
+        let endpoints = discovery.resolve_records_for(None, lookup).await?;
+        #[cfg(feature = "tls")]
+        assert_eq!(
+            endpoints,
+            ["http://b.example.com:2379", "https://a.example.com:2379"]
+        );
+        // HTTPS endpoints cannot be built without TLS
+        #[cfg(not(feature = "tls"))]
+        assert_eq!(endpoints, ["http://b.example.com:2379"]);
+
+        // HTTP endpoints cannot be built with TLS options
+        #[cfg(feature = "tls")]
+        {
+            let options = ConnectOptions::new().with_tls(crate::TlsOptions::new());
+            let endpoints = discovery
+                .resolve_records_for(Some(&options), lookup)
+                .await?;
+            assert_eq!(endpoints, ["https://a.example.com:2379"]);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! Endpoints of the channel shared by all clones of a client.
+
+use crate::client::{Client, ConnectOptions};
+use crate::error::{Error, Result};
+use crate::rpc::maintenance::MaintenanceClient;
+use http::{HeaderMap, HeaderValue};
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, Sender, UnboundedSender};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use tonic::transport::{Channel, Endpoint};
+use tower::discover::Change;
+
+/// Minimum capacity of the queue of endpoint changes of the channel.
+/// Changes are only consumed while requests are sent, so they may fill it up:
+/// they are first queued without bound, and forwarded to the channel in order.
+const CHANGE_CAPACITY: usize = 1024;
+
+/// Interval between checks of the endpoints evicted for having no leader.
+const READMIT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The endpoints balanced by the channel and the options they are built with.
+#[derive(Debug)]
+struct PoolState {
+    options: Option<ConnectOptions>,
+    /// endpoints in the channel
+    active: BTreeSet<String>,
+    /// endpoints evicted for having no leader
+    evicted: BTreeSet<String>,
+    /// whether a task is checking the evicted endpoints
+    readmitting: bool,
+}
+
+/// Endpoints of the channel shared by a client, its clones and its service clients.
+///
+/// Changing the endpoints or their options replaces the connections in place:
+/// requests sent afterwards use the new connections, while requests and streams
+/// in flight finish on the old ones.
+#[derive(Debug)]
+pub struct EndpointPool {
+    changes: UnboundedSender<Change<String, Endpoint>>,
+    /// queue of the changes of the channel, to detect that it is dropped
+    channel_changes: Sender<Change<String, Endpoint>>,
+    state: Mutex<PoolState>,
+    auth_token: RwLock<Option<Arc<HeaderValue>>>,
+    metadata: Option<Arc<HeaderMap>>,
+    /// serializes the leader checks
+    leader_check: Mutex<()>,
+}
+
+impl EndpointPool {
+    /// Connects a channel balanced over `endpoints`, or over the discovered endpoints
+    /// if configured in `options`.
+    pub(crate) async fn connect(
+        endpoints: Vec<String>,
+        options: Option<ConnectOptions>,
+        metadata: Option<Arc<HeaderMap>>,
+    ) -> Result<(Channel, Arc<Self>)> {
+        #[cfg(feature = "discovery-srv")]
+        let discovery = options
+            .as_ref()
+            .and_then(|options| options.discovery_srv().cloned());
+        #[cfg(feature = "discovery-srv")]
+        let endpoints = match &discovery {
+            Some(discovery) => discovery.resolve_for(options.as_ref()).await?,
+            None => endpoints,
+        };
+
+        let built = endpoints
+            .iter()
+            .map(|e| Ok((e.clone(), Client::build_endpoint(e, options.as_ref())?)))
+            .collect::<Result<Vec<_>>>()?;
+        let gateway = matches!(&options, Some(options) if options.uses_gateway());
+        match built.as_slice() {
+            [] => return Err(Error::InvalidArgs(String::from("empty endpoints"))),
+            // fail early when the only endpoint is unreachable over gRPC,
+            // unless the requests go through the gateway
+            [(_, endpoint)] if !gateway => {
+                endpoint.connect().await?;
+            }
+            _ => {}
+        }
+
+        let (channel, channel_changes) = Channel::balance_channel(built.len().max(CHANGE_CAPACITY));
+        let mut active = BTreeSet::new();
+        for (key, endpoint) in built {
+            // cannot fail, the queue has room for all endpoints
+            let _ = channel_changes.try_send(Change::Insert(key.clone(), endpoint));
+            active.insert(key);
+        }
+
+        let (changes, mut queued) = unbounded_channel();
+        let forwarded = channel_changes.clone();
+        tokio::spawn(async move {
+            while let Some(change) = queued.recv().await {
+                if forwarded.send(change).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        let pool = Arc::new(Self {
+            changes,
+            channel_changes,
+            state: Mutex::new(PoolState {
+                options,
+                active,
+                evicted: BTreeSet::new(),
+                readmitting: false,
+            }),
+            auth_token: RwLock::new(None),
+            metadata,
+            leader_check: Mutex::new(()),
+        });
+
+        #[cfg(feature = "discovery-srv")]
+        if let Some(discovery) = discovery {
+            tokio::spawn(discovery.refresh(pool.clone()));
+        }
+        Ok((channel, pool))
+    }
+
+    /// The authentication token of the requests.
+    #[inline]
+    pub(crate) fn auth_token(&self) -> Option<Arc<HeaderValue>> {
+        self.auth_token
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Sets the authentication token of the requests.
+    #[inline]
+    pub(crate) fn set_auth_token(&self, token: Option<Arc<HeaderValue>>) {
+        *self.auth_token.write().unwrap_or_else(|e| e.into_inner()) = token;
+    }
+
+    /// The default metadata of the requests.
+    #[inline]
+    pub(crate) fn metadata(&self) -> Option<Arc<HeaderMap>> {
+        self.metadata.clone()
+    }
+
+    /// The options the endpoints are built with.
+    #[inline]
+    pub(crate) async fn options(&self) -> Option<ConnectOptions> {
+        self.state.lock().await.options.clone()
+    }
+
+    /// The endpoints balanced by the channel.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) async fn endpoints(&self) -> Vec<String> {
+        self.state.lock().await.active.iter().cloned().collect()
+    }
+
+    /// Whether the channel is dropped.
+    #[inline]
+    pub(crate) fn is_closed(&self) -> bool {
+        self.channel_changes.is_closed()
+    }
+
+    /// Rebuilds the endpoints with `options`, replacing their connections.
+    #[cfg(feature = "tls")]
+    pub(crate) async fn reload(&self, options: ConnectOptions) -> Result<()> {
+        let mut state = self.state.lock().await;
+        let built = state
+            .active
+            .iter()
+            .map(|e| Ok((e.clone(), Client::build_endpoint(e, Some(&options))?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        for (key, endpoint) in built {
+            self.send(Change::Insert(key, endpoint))?;
+        }
+        state.options = Some(options);
+        Ok(())
+    }
+
+    /// Replaces the endpoints by the `resolved` ones, keeping the evicted ones out.
+    /// The endpoints that cannot be built are skipped.
+    #[cfg(feature = "discovery-srv")]
+    pub(crate) async fn update(&self, resolved: BTreeSet<String>) -> Result<()> {
+        let mut state = self.state.lock().await;
+        let state = &mut *state;
+        state.evicted.retain(|e| resolved.contains(e));
+
+        let mut active = state
+            .active
+            .intersection(&resolved)
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for e in resolved.difference(&state.active) {
+            if state.evicted.contains(e) {
+                continue;
+            }
+            if let Ok(endpoint) = Client::build_endpoint(e, state.options.as_ref()) {
+                self.send(Change::Insert(e.clone(), endpoint))?;
+                active.insert(e.clone());
+            }
+        }
+        for e in state.active.difference(&resolved) {
+            self.send(Change::Remove(e.clone()))?;
+        }
+        state.active = active;
+        Ok(())
+    }
+
+    /// Evicts the endpoints whose member has no leader, unless none has one.
+    /// Returns whether any endpoint was evicted.
+    pub(crate) async fn check_leaders(self: &Arc<Self>) -> bool {
+        let _check = self.leader_check.lock().await;
+        let (endpoints, options) = {
+            let state = self.state.lock().await;
+            (state.active.clone(), state.options.clone())
+        };
+
+        let mut leaderless = BTreeSet::new();
+        for e in &endpoints {
+            if !self.has_leader(e, options.as_ref()).await {
+                leaderless.insert(e.clone());
+            }
+        }
+        if leaderless.is_empty() || leaderless.len() == endpoints.len() {
+            return false;
+        }
+
+        let mut state = self.state.lock().await;
+        for e in &leaderless {
+            // skip the endpoints removed meanwhile
+            if !state.active.remove(e) {
+                continue;
+            }
+            if self.send(Change::Remove(e.clone())).is_err() {
+                return false;
+            }
+            state.evicted.insert(e.clone());
+        }
+        if !state.readmitting {
+            state.readmitting = true;
+            tokio::spawn(self.clone().readmit());
+        }
+        true
+    }
+
+    /// Checks the leaders in the background, after a stream lost its leader.
+    #[inline]
+    pub(crate) fn spawn_leader_check(self: &Arc<Self>) {
+        let pool = self.clone();
+        tokio::spawn(async move { pool.check_leaders().await });
+    }
+
+    /// Puts the evicted endpoints back once their member has a leader again.
+    async fn readmit(self: Arc<Self>) {
+        loop {
+            sleep(READMIT_INTERVAL).await;
+            if self.is_closed() {
+                return;
+            }
+
+            let (evicted, options) = {
+                let state = self.state.lock().await;
+                (state.evicted.clone(), state.options.clone())
+            };
+            let mut recovered = Vec::new();
+            for e in evicted {
+                if self.has_leader(&e, options.as_ref()).await {
+                    recovered.push(e);
+                }
+            }
+
+            let mut state = self.state.lock().await;
+            for e in recovered {
+                // skip the endpoints no longer discovered
+                if !state.evicted.remove(&e) {
+                    continue;
+                }
+                if let Ok(endpoint) = Client::build_endpoint(&e, state.options.as_ref()) {
+                    if self.send(Change::Insert(e.clone(), endpoint)).is_err() {
+                        return;
+                    }
+                    state.active.insert(e);
+                }
+            }
+            if state.evicted.is_empty() {
+                state.readmitting = false;
+                return;
+            }
+        }
+    }
+
+    /// Whether the member of `endpoint` is reachable and has a leader.
+    async fn has_leader(&self, endpoint: &str, options: Option<&ConnectOptions>) -> bool {
+        let channel = match Client::connect_channel(&[endpoint], options).await {
+            Ok(channel) => channel,
+            Err(_) => return false,
+        };
+        let maintenance = MaintenanceClient::new(channel, self.auth_token(), self.metadata());
+        matches!(maintenance.status().await, Ok(status) if status.leader() != 0)
+    }
+
+    /// Queues a change of the endpoints of the channel, without waiting for its room.
+    #[inline]
+    fn send(&self, change: Change<String, Endpoint>) -> Result<()> {
+        self.changes
+            .send(change)
+            .map_err(|_| Error::IoError(ErrorKind::BrokenPipe.into()))
+    }
+}
@@ -0,0 +1,188 @@
+//! Client configuration from the `ETCDCTL_*` environment variables of etcdctl.
+
+use crate::client::ConnectOptions;
+use crate::error::{Error, Result};
+
+/// Endpoints of etcdctl when `ETCDCTL_ENDPOINTS` is not set.
+const DEFAULT_ENDPOINTS: &str = "127.0.0.1:2379";
+
+/// Parses a boolean like `strconv.ParseBool` of Go.
+fn parse_bool(name: &str, value: &str) -> Result<bool> {
+    match value {
+        "1" | "t" | "T" | "TRUE" | "true" | "True" => Ok(true),
+        "0" | "f" | "F" | "FALSE" | "false" | "False" => Ok(false),
+        _ => Err(Error::InvalidArgs(format!(
+            "invalid boolean {:?} of {}",
+            value, name
+        ))),
+    }
+}
+
+/// Reads the endpoints and connect options from the variables returned by `lookup`.
+///
+/// Empty variables are treated as unset, like etcdctl does.
+pub(crate) fn from_lookup<F>(lookup: F) -> Result<(Vec<String>, ConnectOptions)>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+
+    let endpoints = var("ETCDCTL_ENDPOINTS")
+        .unwrap_or_else(|| DEFAULT_ENDPOINTS.to_string())
+        .split(',')
+        .map(str::to_string)
+        .collect();
+
+    let mut options = ConnectOptions::new();
+    match (var("ETCDCTL_USER"), var("ETCDCTL_PASSWORD")) {
+        (Some(user), None) => match user.split_once(':') {
+            Some((name, password)) => options = options.with_user(name, password),
+            None => {
+                return Err(Error::InvalidArgs(String::from(
+                    "ETCDCTL_USER must be user:password when ETCDCTL_PASSWORD is not set",
+                )))
+            }
+        },
+        // the password may contain ':' when given separately
+        (Some(user), Some(password)) => options = options.with_user(user, password),
+        (None, Some(_)) => {
+            return Err(Error::InvalidArgs(String::from(
+                "ETCDCTL_PASSWORD is set but ETCDCTL_USER is not",
+            )))
+        }
+        (None, None) => {}
+    }
+
+    #[cfg(feature = "tls")]
+    {
+        if let Some(path) = var("ETCDCTL_CACERT") {
+            options = options.with_ca_cert_file(path);
+        }
+        match (var("ETCDCTL_CERT"), var("ETCDCTL_KEY")) {
+            (Some(cert), Some(key)) => options = options.with_client_cert_files(cert, key),
+            (None, None) => {}
+            _ => {
+                return Err(Error::InvalidArgs(String::from(
+                    "ETCDCTL_CERT and ETCDCTL_KEY must be set together",
+                )))
+            }
+        }
+        if let Some(value) = var("ETCDCTL_INSECURE_SKIP_TLS_VERIFY") {
+            if parse_bool("ETCDCTL_INSECURE_SKIP_TLS_VERIFY", &value)? {
+                options = options.with_insecure_skip_tls_verify();
+            }
+        }
+        if let Some(value) = var("ETCDCTL_INSECURE_TRANSPORT") {
+            // TLS is used anyway once TLS files are given
+            if !parse_bool("ETCDCTL_INSECURE_TRANSPORT", &value)? {
+                options = options.with_tls(crate::TlsOptions::new());
+            }
+        }
+    }
+
+    #[cfg(feature = "discovery-srv")]
+    if let Some(domain) = var("ETCDCTL_DISCOVERY_SRV") {
+        let mut discovery = crate::SrvDiscovery::new(domain);
+        if let Some(name) = var("ETCDCTL_DISCOVERY_SRV_NAME") {
+            discovery = discovery.with_service_name(name);
+        }
+        options = options.with_discovery_srv(discovery);
+    }
+    #[cfg(not(feature = "discovery-srv"))]
+    if var("ETCDCTL_DISCOVERY_SRV").is_some() {
+        return Err(Error::InvalidArgs(String::from(
+            "ETCDCTL_DISCOVERY_SRV is only supported with the feature \"discovery-srv\"",
+        )));
+    }
+
+    #[cfg(not(feature = "tls"))]
+    if let Some(value) = var("ETCDCTL_INSECURE_TRANSPORT") {
+        if !parse_bool("ETCDCTL_INSECURE_TRANSPORT", &value)? {
+            return Err(Error::InvalidArgs(String::from(
+                "ETCDCTL_INSECURE_TRANSPORT=false is only supported with the feature \"tls\"",
+            )));
+        }
+    }
+    #[cfg(not(feature = "tls"))]
+    for name in &[
+        "ETCDCTL_CACERT",
+        "ETCDCTL_CERT",
+        "ETCDCTL_KEY",
+        "ETCDCTL_INSECURE_SKIP_TLS_VERIFY",
+    ] {
+        if var(name).is_some() {
+            return Err(Error::InvalidArgs(format!(
+                "{} is only supported with the feature \"tls\"",
+                name
+            )));
+        }
+    }
+
+    Ok((endpoints, options))
+}
+
+/// Reads the endpoints and connect options from the environment variables.
+#[inline]
+pub(crate) fn from_env() -> Result<(Vec<String>, ConnectOptions)> {
+    from_lookup(|name| std::env::var(name).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup(vars: &[(&str, &str)]) -> Result<(Vec<String>, ConnectOptions)> {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_from_lookup() -> Result<()> {
+        let (endpoints, _) = lookup(&[])?;
+        assert_eq!(endpoints, vec!["127.0.0.1:2379"]);
+
+        let (endpoints, _) = lookup(&[(
+            "ETCDCTL_ENDPOINTS",
+            "http://10.0.0.1:2379,http://10.0.0.2:2379",
+        )])?;
+        assert_eq!(
+            endpoints,
+            vec!["http://10.0.0.1:2379", "http://10.0.0.2:2379"]
+        );
+
+        let (_, options) = lookup(&[("ETCDCTL_USER", "root:pass:word")])?;
+        assert_eq!(options.user(), Some(("root", "pass:word")));
+        let (_, options) = lookup(&[("ETCDCTL_USER", "root"), ("ETCDCTL_PASSWORD", "a:b")])?;
+        assert_eq!(options.user(), Some(("root", "a:b")));
+        let (_, options) = lookup(&[("ETCDCTL_USER", "")])?;
+        assert_eq!(options.user(), None);
+
+        assert!(lookup(&[("ETCDCTL_USER", "root")]).is_err());
+        assert!(lookup(&[("ETCDCTL_PASSWORD", "pass")]).is_err());
+
+        #[cfg(feature = "tls")]
+        {
+            lookup(&[
+                ("ETCDCTL_CACERT", "ca.pem"),
+                ("ETCDCTL_CERT", "cert.pem"),
+                ("ETCDCTL_KEY", "key.pem"),
+                ("ETCDCTL_INSECURE_SKIP_TLS_VERIFY", "true"),
+                ("ETCDCTL_INSECURE_TRANSPORT", "false"),
+            ])?;
+            assert!(lookup(&[("ETCDCTL_CERT", "cert.pem")]).is_err());
+            assert!(lookup(&[("ETCDCTL_INSECURE_TRANSPORT", "nope")]).is_err());
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            assert!(lookup(&[("ETCDCTL_CACERT", "ca.pem")]).is_err());
+            assert!(lookup(&[("ETCDCTL_INSECURE_TRANSPORT", "false")]).is_err());
+            lookup(&[("ETCDCTL_INSECURE_TRANSPORT", "true")])?;
+        }
+
+        Ok(())
+    }
+}
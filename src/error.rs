@@ -1,10 +1,15 @@
 //! Etcd Client Error handling.
 
+use crate::ops::defrag::RollingDefragReport;
 use std::fmt::{Display, Formatter};
 use std::str::Utf8Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Message of etcd's `ErrGRPCUserNotFound`, sent with `Code::FailedPrecondition`.
+#[cfg(feature = "tls")]
+pub(crate) const USER_NOT_FOUND: &str = "etcdserver: user name not found";
+
 /// The error type for `etcd` client.
 #[derive(Debug)]
 pub enum Error {
@@ -35,6 +40,38 @@ pub enum Error {
     /// Election error
     ElectError(String),
 
+    /// Barrier error
+    BarrierError(String),
+
+    /// Snapshot error
+    SnapshotError(String),
+
+    /// Defragmentation error
+    DefragError(String),
+
+    /// Rolling defragmentation aborted by an error
+    RollingDefragError {
+        /// the members processed before the error
+        report: Box<RollingDefragReport>,
+        /// the error that aborted the rollout
+        error: Box<Error>,
+    },
+
+    /// Membership change error
+    MembershipError(String),
+
+    /// Leader transfer error
+    LeaderTransferError(String),
+
+    /// Endpoint discovery error
+    DiscoveryError(String),
+
+    /// grpc-gateway transport error
+    GatewayError(String),
+
+    /// The member serving a stream that requires a leader lost its leader
+    NoLeader(String),
+
     /// Invalid header value
     InvalidHeaderValue(http::header::InvalidHeaderValue),
 }
@@ -52,6 +89,20 @@ impl Display for Error {
             Error::Utf8Error(e) => write!(f, "utf8 error: {}", e),
             Error::LeaseKeepAliveError(e) => write!(f, "lease keep alive error: {}", e),
             Error::ElectError(e) => write!(f, "election error: {}", e),
+            Error::BarrierError(e) => write!(f, "barrier error: {}", e),
+            Error::SnapshotError(e) => write!(f, "snapshot error: {}", e),
+            Error::DefragError(e) => write!(f, "defragmentation error: {}", e),
+            Error::RollingDefragError { report, error } => write!(
+                f,
+                "rolling defragmentation aborted after {} members: {}",
+                report.members().len(),
+                error
+            ),
+            Error::MembershipError(e) => write!(f, "membership error: {}", e),
+            Error::LeaderTransferError(e) => write!(f, "leader transfer error: {}", e),
+            Error::DiscoveryError(e) => write!(f, "discovery error: {}", e),
+            Error::GatewayError(e) => write!(f, "gateway error: {}", e),
+            Error::NoLeader(e) => write!(f, "no leader: {}", e),
             Error::InvalidHeaderValue(e) => write!(f, "invalid metadata value: {}", e),
         }
     }
@@ -59,6 +110,27 @@ impl Display for Error {
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// Whether etcd rejected the request because the user does not exist.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn is_user_not_found(&self) -> bool {
+        matches!(self, Error::GRpcStatus(status)
+            if status.code() == tonic::Code::FailedPrecondition
+                && status.message() == USER_NOT_FOUND)
+    }
+
+    /// Converts the status ending a stream, recognizing the cancellation of streams
+    /// requiring a leader when the member loses it.
+    pub(crate) fn from_stream_status(status: tonic::Status) -> Self {
+        if status.code() == tonic::Code::Unavailable && status.message().contains("no leader") {
+            Error::NoLeader(status.message().to_string())
+        } else {
+            Error::GRpcStatus(status)
+        }
+    }
+}
+
 impl From<http::uri::InvalidUri> for Error {
     #[inline]
     fn from(e: http::uri::InvalidUri) -> Self {
@@ -12,6 +12,11 @@
 //! - Lock
 //! - Election
 //!
+//! # Recipes
+//!
+//! - Barrier
+//! - DoubleBarrier
+//!
 //! # Usage
 //!
 //! Add this to your `Cargo.toml`:
@@ -29,7 +34,7 @@
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Error> {
-//!     let mut client = Client::connect(["localhost:2379"], None).await?;
+//!     let client = Client::connect(["localhost:2379"], None).await?;
 //!     // put kv
 //!     client.put("foo", "bar", None).await?;
 //!     // get kv
@@ -48,22 +53,66 @@
 //!
 //! # Feature Flags
 //!
-//! - `tls`: Enables the `rustls`-based TLS connection. Not
-//! enabled by default.
+//! - `tls`: Enables the `rustls`-based TLS connection, configured with `TlsOptions` or from PEM
+//! files like the TLS flags of etcdctl, including authentication by client certificate with
+//! `ConnectOptions::with_client_cert_auth` and reloading of TLS credentials with
+//! `Client::reload_tls`. Not enabled by default.
 //! - `tls-roots`: Adds system trust roots to `rustls`-based TLS connection using the
 //! `rustls-native-certs` crate. Not enabled by default.
 //! - `pub-response-field`: Exposes structs used to create regular `etcd-client` responses
 //! including internal protobuf representations. Useful for mocking. Not enabled by default.
+//! - `blocking`: Provides a synchronous client in the `blocking` module, backed by a multi-threaded
+//! tokio runtime owned by the client. Not enabled by default.
+//! - `serde`: Implements `Serialize` and `Deserialize` for the RBAC desired-state types, so that
+//! they can be loaded from TOML, YAML, JSON, etc. Not enabled by default.
+//! - `discovery-srv`: Discovers the endpoints from DNS SRV records with `SrvDiscovery`, like
+//! `--discovery-srv` of etcd. Not enabled by default.
+//! - `compression`: Enables `gzip` compression of gRPC messages, configured with
+//! `ConnectOptions::with_compression`. Not enabled by default.
+//! - `gateway`: Provides `GatewayClient`, which sends KV, transaction, lease, watch and auth
+//! requests to the grpc-gateway of etcd as JSON over HTTP/1.1, for networks without HTTP/2,
+//! and over HTTPS with the `tls` feature. `ConnectOptions::with_gateway` sends them through
+//! the gateway from `Client` too. Not enabled by default.
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 mod auth;
+#[cfg(feature = "blocking")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+pub mod blocking;
 mod client;
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "discovery-srv")]
+mod discovery;
+mod endpoints;
+mod env;
 mod error;
+mod limit;
+mod ops;
+mod recipes;
 mod rpc;
+#[cfg(feature = "tls")]
+mod tls;
 
 pub use crate::client::{Client, ConnectOptions};
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+pub use crate::compression::{CompressionOptions, RpcService};
+#[cfg(feature = "discovery-srv")]
+#[cfg_attr(docsrs, doc(cfg(feature = "discovery-srv")))]
+pub use crate::discovery::SrvDiscovery;
 pub use crate::error::Error;
+pub use crate::limit::RateLimit;
+pub use crate::ops::access::{AuditFinding, Grant, RbacEvaluator};
+pub use crate::ops::alarm::{AlarmEvent, AlarmMonitor};
+pub use crate::ops::compactor::{CompactionMode, Compactor, CompactorOptions};
+pub use crate::ops::consistency::{ConsistencyCheckOptions, ConsistencyReport};
+pub use crate::ops::defrag::{MemberDefragResult, RollingDefragOptions, RollingDefragReport};
+pub use crate::ops::leader::TransferLeaderOptions;
+pub use crate::ops::membership::MembershipOptions;
+pub use crate::ops::rbac::{PermissionSpec, RbacChange, RbacConfig, RbacPlan, RoleSpec, UserSpec};
+pub use crate::recipes::barrier::{Barrier, DoubleBarrier};
 pub use crate::rpc::auth::{
     AuthClient, AuthDisableResponse, AuthEnableResponse, Permission, PermissionType,
     RoleAddResponse, RoleDeleteResponse, RoleGetResponse, RoleGrantPermissionResponse,
@@ -79,6 +128,9 @@ pub use crate::rpc::election::{
     CampaignResponse, ElectionClient, LeaderKey, LeaderResponse, ObserveStream, ProclaimOptions,
     ProclaimResponse, ResignOptions, ResignResponse,
 };
+#[cfg(feature = "gateway")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gateway")))]
+pub use crate::rpc::gateway::{GatewayClient, GatewayWatchStream};
 pub use crate::rpc::kv::{
     CompactionOptions, CompactionResponse, Compare, CompareOp, DeleteOptions, DeleteResponse,
     GetOptions, GetResponse, KvClient, PutOptions, PutResponse, SortOrder, SortTarget, Txn, TxnOp,
@@ -96,8 +148,8 @@ pub use crate::rpc::maintenance::{
     SnapshotStreaming, StatusResponse,
 };
 pub use crate::rpc::watch::{
-    Event, EventType, WatchClient, WatchFilterType, WatchOptions, WatchResponse, WatchStream,
-    Watcher,
+    Event, EventChange, EventType, WatchClient, WatchFilterType, WatchOptions, WatchResponse,
+    WatchStream, Watcher,
 };
 pub use crate::rpc::{KeyValue, ResponseHeader};
 
@@ -105,6 +157,10 @@ pub use crate::rpc::{KeyValue, ResponseHeader};
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 pub use tonic::transport::{Certificate, ClientTlsConfig as TlsOptions, Identity};
 
+#[cfg(feature = "tls")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+pub use crate::tls::TlsReloader;
+
 /// Exposes internal protobuf representations used to create regular public response types.
 #[cfg(feature = "pub-response-field")]
 #[cfg_attr(docsrs, doc(cfg(feature = "pub-response-field")))]
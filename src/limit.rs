@@ -0,0 +1,411 @@
+//! Client-side rate limiting and concurrency caps.
+
+use crate::error::Error;
+use http::{HeaderMap, Request, Response};
+use http_body::{Body, SizeHint};
+use std::error::Error as StdError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{sleep, Instant};
+use tonic::Status;
+use tower::ServiceExt;
+use tower_service::Service;
+
+/// Limits of a class of requests: a token bucket rate and a maximum of requests in flight.
+///
+/// By default nothing is limited. Requests over the limits wait in queue, or fail
+/// with `Code::ResourceExhausted` once [`RateLimit::with_fail_fast`] is set.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    rate: Option<f64>,
+    burst: Option<u32>,
+    max_in_flight: Option<usize>,
+    fail_fast: bool,
+}
+
+impl RateLimit {
+    /// Creates a `RateLimit` limiting nothing.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            rate: None,
+            burst: None,
+            max_in_flight: None,
+            fail_fast: false,
+        }
+    }
+
+    /// Allows `per_second` requests per second on average.
+    /// The rate must be positive, connecting fails otherwise.
+    #[inline]
+    pub const fn with_rate(mut self, per_second: f64) -> Self {
+        self.rate = Some(per_second);
+        self
+    }
+
+    /// Allows bursts of `burst` requests over the rate. Defaults to one second of requests.
+    #[inline]
+    pub const fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = Some(burst);
+        self
+    }
+
+    /// Allows at most `max` requests in flight, which must be at least 1.
+    /// A request is in flight until the body of its response is dropped.
+    #[inline]
+    pub const fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = Some(max);
+        self
+    }
+
+    /// Fails requests over the limits instead of queueing them.
+    #[inline]
+    pub const fn with_fail_fast(mut self) -> Self {
+        self.fail_fast = true;
+        self
+    }
+
+    /// Whether nothing is limited.
+    #[inline]
+    fn is_unlimited(&self) -> bool {
+        self.rate.is_none() && self.max_in_flight.is_none()
+    }
+}
+
+/// Tokens of the rate, negative when reserved by queued requests.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Enforces a [`RateLimit`], shared by all clones of a client.
+#[derive(Debug)]
+pub struct Limiter {
+    rate: Option<(f64, f64)>,
+    bucket: Mutex<Bucket>,
+    in_flight: Option<Arc<Semaphore>>,
+    fail_fast: bool,
+}
+
+impl Limiter {
+    /// Creates a limiter enforcing `limit`, `None` if nothing is limited.
+    pub(crate) fn new(limit: RateLimit) -> Result<Option<Arc<Self>>, Error> {
+        if let Some(rate) = limit.rate {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(Error::InvalidArgs(format!(
+                    "invalid rate limit {}, must be positive",
+                    rate
+                )));
+            }
+        }
+        if limit.max_in_flight == Some(0) {
+            return Err(Error::InvalidArgs(String::from(
+                "invalid limit of requests in flight 0, must be at least 1",
+            )));
+        }
+        if limit.is_unlimited() {
+            return Ok(None);
+        }
+
+        let rate = limit.rate.map(|rate| {
+            let burst = limit.burst.map_or(rate.ceil(), f64::from).max(1.0);
+            (rate, burst)
+        });
+        Ok(Some(Arc::new(Self {
+            rate,
+            bucket: Mutex::new(Bucket {
+                tokens: rate.map_or(0.0, |(_, burst)| burst),
+                updated: Instant::now(),
+            }),
+            in_flight: limit.max_in_flight.map(|max| Arc::new(Semaphore::new(max))),
+            fail_fast: limit.fail_fast,
+        })))
+    }
+
+    /// Waits for a request to be allowed.
+    /// The returned permit counts the request in flight until dropped.
+    pub(crate) async fn acquire(&self) -> Result<Option<OwnedSemaphorePermit>, Status> {
+        let permit = self.acquire_in_flight().await?;
+        self.acquire_rate().await?;
+        Ok(permit)
+    }
+
+    /// Waits for room in flight, without taking a token of the rate.
+    pub(crate) async fn acquire_in_flight(&self) -> Result<Option<OwnedSemaphorePermit>, Status> {
+        let semaphore = match &self.in_flight {
+            Some(semaphore) => semaphore.clone(),
+            None => return Ok(None),
+        };
+        if self.fail_fast {
+            semaphore
+                .try_acquire_owned()
+                .map(Some)
+                .map_err(|_| Status::resource_exhausted("too many requests in flight"))
+        } else {
+            semaphore
+                .acquire_owned()
+                .await
+                .map(Some)
+                .map_err(|e| Status::internal(e.to_string()))
+        }
+    }
+
+    /// Waits for a token of the rate.
+    pub(crate) async fn acquire_rate(&self) -> Result<(), Status> {
+        let wait = self.take_token()?;
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    /// Takes a token of the rate, returning how long to wait for it.
+    fn take_token(&self) -> Result<Duration, Status> {
+        let (rate, burst) = match self.rate {
+            Some(rate) => rate,
+            None => return Ok(Duration::ZERO),
+        };
+
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(Duration::ZERO)
+        } else if self.fail_fast {
+            Err(Status::resource_exhausted("client rate limit exceeded"))
+        } else {
+            // reserve the token, queueing after the requests already waiting
+            bucket.tokens -= 1.0;
+            Ok(Duration::from_secs_f64(-bucket.tokens / rate))
+        }
+    }
+}
+
+/// Limiters of the classes of requests.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Limits {
+    pub(crate) read: Option<Arc<Limiter>>,
+    pub(crate) write: Option<Arc<Limiter>>,
+    pub(crate) keep_alive: Option<Arc<Limiter>>,
+}
+
+impl Limits {
+    /// Creates the limiters of `read`, `write` and `keep_alive` requests.
+    #[inline]
+    pub(crate) fn new(
+        read: RateLimit,
+        write: RateLimit,
+        keep_alive: RateLimit,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            read: Limiter::new(read)?,
+            write: Limiter::new(write)?,
+            keep_alive: Limiter::new(keep_alive)?,
+        })
+    }
+
+    /// The limiter of the request to the gRPC method `path`.
+    fn limiter(&self, path: &str) -> Option<&Arc<Limiter>> {
+        match path {
+            "/etcdserverpb.KV/Range" => self.read.as_ref(),
+            // a transaction may write, like etcd counts it
+            "/etcdserverpb.KV/Put"
+            | "/etcdserverpb.KV/DeleteRange"
+            | "/etcdserverpb.KV/Txn"
+            | "/etcdserverpb.KV/Compact" => self.write.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Body of a response, counting its request in flight until the body is dropped.
+#[derive(Debug)]
+pub(crate) struct LimitBody<B> {
+    inner: B,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<B: Body + Unpin> Body for LimitBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    #[inline]
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    #[inline]
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Applies the read and write limits to the requests of the inner service.
+///
+/// A request counts in flight until the body of its response is read and dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct LimitService<S> {
+    inner: S,
+    limits: Limits,
+}
+
+impl<S> LimitService<S> {
+    #[inline]
+    pub(crate) fn new(inner: S, limits: Limits) -> Self {
+        Self { inner, limits }
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for LimitService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn StdError + Send + Sync>>,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+{
+    type Response = Response<LimitBody<ResBody>>;
+    type Error = Box<dyn StdError + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // the inner service is polled once the request is allowed
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let limiter = self.limits.limiter(request.uri().path()).cloned();
+        let mut inner = self.inner.clone();
+        Box::pin(async move {
+            let permit = match limiter {
+                Some(limiter) => limiter.acquire().await?,
+                None => None,
+            };
+            let response = inner
+                .ready()
+                .await
+                .map_err(Into::into)?
+                .call(request)
+                .await
+                .map_err(Into::into)?;
+            Ok(response.map(|inner| LimitBody {
+                inner,
+                _permit: permit,
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    #[tokio::test]
+    async fn test_rate() {
+        let limiter = Limiter::new(RateLimit::new().with_rate(10.0).with_burst(2))
+            .unwrap()
+            .unwrap();
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await.unwrap();
+        }
+        // two tokens of the burst, then two at 10 per second
+        assert!(start.elapsed() >= Duration::from_millis(190));
+
+        let limiter = Limiter::new(RateLimit::new().with_rate(1.0).with_fail_fast())
+            .unwrap()
+            .unwrap();
+        assert!(limiter.acquire().await.is_ok());
+        let status = limiter.acquire().await.unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight() {
+        assert!(Limiter::new(RateLimit::new().with_fail_fast())
+            .unwrap()
+            .is_none());
+
+        let limiter = Limiter::new(RateLimit::new().with_max_in_flight(1).with_fail_fast())
+            .unwrap()
+            .unwrap();
+        let permit = limiter.acquire().await.unwrap();
+        assert!(limiter.acquire().await.is_err());
+        drop(permit);
+        assert!(limiter.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_until_body_dropped() {
+        let limits = Limits::new(
+            RateLimit::new(),
+            RateLimit::new().with_max_in_flight(1).with_fail_fast(),
+            RateLimit::new(),
+        )
+        .unwrap();
+        let inner = tower::service_fn(|_: Request<()>| async {
+            Ok::<_, Status>(Response::new(String::from("response")))
+        });
+        let mut service = LimitService::new(inner, limits);
+        let put = || Request::post("/etcdserverpb.KV/Put").body(()).unwrap();
+
+        let response = service.call(put()).await.unwrap();
+        assert!(service.call(put()).await.is_err());
+        drop(response);
+        assert!(service.call(put()).await.is_ok());
+    }
+
+    #[test]
+    fn test_limiter_of_path() {
+        let limits = Limits::new(
+            RateLimit::new().with_rate(1.0),
+            RateLimit::new(),
+            RateLimit::new().with_max_in_flight(1),
+        )
+        .unwrap();
+        assert!(limits.limiter("/etcdserverpb.KV/Range").is_some());
+        assert!(limits.limiter("/etcdserverpb.KV/Put").is_none());
+        assert!(limits
+            .limiter("/etcdserverpb.Lease/LeaseKeepAlive")
+            .is_none());
+        assert!(limits.keep_alive.is_some());
+    }
+
+    #[test]
+    fn test_invalid_limits() {
+        for rate in &[0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let limit = RateLimit::new().with_rate(*rate);
+            assert!(matches!(Limiter::new(limit), Err(Error::InvalidArgs(_))));
+        }
+        let limit = RateLimit::new().with_max_in_flight(0);
+        assert!(matches!(Limiter::new(limit), Err(Error::InvalidArgs(_))));
+        let limits = Limits::new(RateLimit::new(), limit, RateLimit::new());
+        assert!(matches!(limits, Err(Error::InvalidArgs(_))));
+    }
+}
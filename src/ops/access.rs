@@ -0,0 +1,404 @@
+//! Offline RBAC access evaluation and audit.
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::rpc::auth::PermissionType;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// The root role is granted access to all keys.
+const ROOT_ROLE: &str = "root";
+
+/// A key range granted to a role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grant {
+    role: String,
+    perm_type: PermissionType,
+    key: Vec<u8>,
+    range_end: Vec<u8>,
+}
+
+impl Grant {
+    /// The role granting the range.
+    #[inline]
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+
+    /// The operation type granted.
+    #[inline]
+    pub const fn perm_type(&self) -> PermissionType {
+        self.perm_type
+    }
+
+    /// The key of the range.
+    #[inline]
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// The range end. Empty for a single key, `\0` for all keys >= key.
+    #[inline]
+    pub fn range_end(&self) -> &[u8] {
+        &self.range_end
+    }
+
+    /// Exclusive end of the range, `None` if unbounded.
+    fn end(&self) -> Option<Vec<u8>> {
+        if self.range_end.is_empty() {
+            let mut end = self.key.clone();
+            end.push(b'\0');
+            Some(end)
+        } else if self.range_end == [b'\0'] {
+            None
+        } else {
+            Some(self.range_end.clone())
+        }
+    }
+
+    /// Whether `key` is before the end of the range.
+    fn before_end(&self, key: &[u8]) -> bool {
+        match self.end() {
+            Some(end) => key < end.as_slice(),
+            None => true,
+        }
+    }
+
+    /// Whether the range contains `key`.
+    fn contains(&self, key: &[u8]) -> bool {
+        key >= self.key.as_slice() && self.before_end(key)
+    }
+
+    /// Whether the range intersects the range of `other`.
+    fn overlaps(&self, other: &Grant) -> bool {
+        other.before_end(&self.key) && self.before_end(&other.key)
+    }
+
+    /// Whether the range covers the whole keyspace.
+    fn is_all_keys(&self) -> bool {
+        self.key == [b'\0'] && self.range_end == [b'\0']
+    }
+
+    /// Whether the grant allows the operation `perm_type`.
+    fn allows(&self, perm_type: PermissionType) -> bool {
+        self.perm_type == PermissionType::Readwrite || self.perm_type == perm_type
+    }
+}
+
+impl Display for Grant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} [{}, {}) of role {}",
+            self.perm_type,
+            String::from_utf8_lossy(&self.key),
+            String::from_utf8_lossy(&self.range_end),
+            self.role
+        )
+    }
+}
+
+/// Whether grants of the types `a` and `b` share an operation.
+#[inline]
+fn share_operation(a: PermissionType, b: PermissionType) -> bool {
+    a == b || a == PermissionType::Readwrite || b == PermissionType::Readwrite
+}
+
+/// Grants of a user, sorted by key.
+#[derive(Debug, Clone, Default)]
+struct UserAccess {
+    roles: Vec<String>,
+    grants: Vec<Grant>,
+}
+
+impl UserAccess {
+    /// Whether the user has the root role.
+    #[inline]
+    fn is_root(&self) -> bool {
+        self.roles.iter().any(|role| role == ROOT_ROLE)
+    }
+}
+
+/// A finding of [`RbacEvaluator::audit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditFinding {
+    /// The user has the root role and thus access to everything.
+    RootUser(String),
+    /// The grant allows writing all keys.
+    AllKeysWrite(Grant),
+    /// The grant allows writing all keys from its key on, without an upper bound.
+    UnboundedWrite(Grant),
+    /// Two grants of the same user cover overlapping ranges for a shared operation.
+    Overlapping {
+        /// The user holding both grants.
+        user: String,
+        /// The grant with the lower key.
+        first: Grant,
+        /// The grant overlapping `first`.
+        second: Grant,
+    },
+}
+
+impl Display for AuditFinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditFinding::RootUser(user) => write!(f, "user {} has the root role", user),
+            AuditFinding::AllKeysWrite(grant) => write!(f, "{} writes all keys", grant),
+            AuditFinding::UnboundedWrite(grant) => write!(f, "{} writes an unbounded range", grant),
+            AuditFinding::Overlapping {
+                user,
+                first,
+                second,
+            } => write!(f, "user {}: {} overlaps {}", user, first, second),
+        }
+    }
+}
+
+/// Snapshot of the users and roles of the cluster, answering access queries offline.
+///
+/// Only the grants are evaluated: when authentication is disabled, etcd allows everything.
+#[derive(Debug, Clone, Default)]
+pub struct RbacEvaluator {
+    roles: HashMap<String, Vec<Grant>>,
+    users: HashMap<String, UserAccess>,
+}
+
+impl RbacEvaluator {
+    /// Builds an evaluator from the roles and users of the cluster.
+    pub(crate) async fn load(client: &Client) -> Result<Self> {
+        let auth = client.auth_client();
+
+        let mut roles = HashMap::new();
+        for role in auth.role_list().await?.roles() {
+            let grants = auth
+                .role_get(role.as_str())
+                .await?
+                .permissions()
+                .iter()
+                .map(|permission| {
+                    let (perm_type, key, range_end) = permission.normalized();
+                    Grant {
+                        role: role.clone(),
+                        perm_type: PermissionType::from(perm_type),
+                        key,
+                        range_end,
+                    }
+                })
+                .collect::<Vec<_>>();
+            roles.insert(role.clone(), grants);
+        }
+
+        let mut users = HashMap::new();
+        for user in auth.user_list().await?.users() {
+            let user_roles = auth.user_get(user.as_str()).await?.roles().to_vec();
+            users.insert(user.clone(), user_roles);
+        }
+
+        Ok(Self::new(roles, users))
+    }
+
+    /// Builds the per-user index of granted ranges.
+    fn new(roles: HashMap<String, Vec<Grant>>, users: HashMap<String, Vec<String>>) -> Self {
+        let users = users
+            .into_iter()
+            .map(|(user, user_roles)| {
+                let mut grants = user_roles
+                    .iter()
+                    .filter_map(|role| roles.get(role))
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                grants.sort_by(|a, b| a.key.cmp(&b.key));
+                (
+                    user,
+                    UserAccess {
+                        roles: user_roles,
+                        grants,
+                    },
+                )
+            })
+            .collect();
+        Self { roles, users }
+    }
+
+    /// Whether `user` may perform `perm_type` on `key`.
+    /// `PermissionType::Readwrite` requires both read and write access.
+    pub fn check(&self, user: &str, key: impl AsRef<[u8]>, perm_type: PermissionType) -> bool {
+        let access = match self.users.get(user) {
+            Some(access) => access,
+            None => return false,
+        };
+        if access.is_root() {
+            return true;
+        }
+
+        let key = key.as_ref();
+        // grants starting after the key cannot contain it
+        let candidates = &access.grants[..access
+            .grants
+            .partition_point(|grant| grant.key.as_slice() <= key)];
+        let allowed = |perm_type| {
+            candidates
+                .iter()
+                .any(|grant| grant.allows(perm_type) && grant.contains(key))
+        };
+        match perm_type {
+            PermissionType::Readwrite => {
+                allowed(PermissionType::Read) && allowed(PermissionType::Write)
+            }
+            _ => allowed(perm_type),
+        }
+    }
+
+    /// Whether `user` may read `key`.
+    #[inline]
+    pub fn can_read(&self, user: &str, key: impl AsRef<[u8]>) -> bool {
+        self.check(user, key, PermissionType::Read)
+    }
+
+    /// Whether `user` may write `key`.
+    #[inline]
+    pub fn can_write(&self, user: &str, key: impl AsRef<[u8]>) -> bool {
+        self.check(user, key, PermissionType::Write)
+    }
+
+    /// The grants of `user` through all of its roles, sorted by key.
+    #[inline]
+    pub fn grants(&self, user: &str) -> &[Grant] {
+        self.users
+            .get(user)
+            .map(|access| access.grants.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Reports root users, overly broad write grants, and overlapping grants.
+    pub fn audit(&self) -> Vec<AuditFinding> {
+        let mut findings = Vec::new();
+
+        let mut users = self.users.iter().collect::<Vec<_>>();
+        users.sort_by(|a, b| a.0.cmp(b.0));
+        for (user, access) in &users {
+            if access.is_root() {
+                findings.push(AuditFinding::RootUser(user.to_string()));
+            }
+        }
+
+        let mut roles = self.roles.iter().collect::<Vec<_>>();
+        roles.sort_by(|a, b| a.0.cmp(b.0));
+        for (_, grants) in roles {
+            for grant in grants.iter().filter(|g| g.allows(PermissionType::Write)) {
+                if grant.is_all_keys() {
+                    findings.push(AuditFinding::AllKeysWrite(grant.clone()));
+                } else if grant.end().is_none() {
+                    findings.push(AuditFinding::UnboundedWrite(grant.clone()));
+                }
+            }
+        }
+
+        for (user, access) in users {
+            for (i, first) in access.grants.iter().enumerate() {
+                for second in &access.grants[i + 1..] {
+                    // sorted by key, no later grant can overlap
+                    if !first.before_end(&second.key) {
+                        break;
+                    }
+                    if share_operation(first.perm_type, second.perm_type) && first.overlaps(second)
+                    {
+                        findings.push(AuditFinding::Overlapping {
+                            user: user.clone(),
+                            first: first.clone(),
+                            second: second.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(role: &str, perm_type: PermissionType, key: &str, range_end: &str) -> Grant {
+        Grant {
+            role: role.to_string(),
+            perm_type,
+            key: key.as_bytes().to_vec(),
+            range_end: range_end.as_bytes().to_vec(),
+        }
+    }
+
+    fn evaluator() -> RbacEvaluator {
+        let mut roles = HashMap::new();
+        roles.insert(
+            "app".to_string(),
+            vec![
+                grant("app", PermissionType::Readwrite, "/app/", "/app0"),
+                grant("app", PermissionType::Read, "/config", ""),
+            ],
+        );
+        roles.insert(
+            "ops".to_string(),
+            vec![
+                grant("ops", PermissionType::Read, "\0", "\0"),
+                grant("ops", PermissionType::Write, "/ops/", "\0"),
+            ],
+        );
+        roles.insert("root".to_string(), Vec::new());
+
+        let mut users = HashMap::new();
+        users.insert("alice".to_string(), vec!["app".to_string()]);
+        users.insert(
+            "bob".to_string(),
+            vec!["app".to_string(), "ops".to_string()],
+        );
+        users.insert("root".to_string(), vec!["root".to_string()]);
+        RbacEvaluator::new(roles, users)
+    }
+
+    #[test]
+    fn test_check() {
+        let evaluator = evaluator();
+
+        assert!(evaluator.can_read("alice", "/app/a"));
+        assert!(evaluator.can_write("alice", "/app/a"));
+        assert!(evaluator.check("alice", "/app/a", PermissionType::Readwrite));
+        assert!(!evaluator.can_read("alice", "/app0"));
+        assert!(evaluator.can_read("alice", "/config"));
+        assert!(!evaluator.can_write("alice", "/config"));
+        assert!(!evaluator.can_read("alice", "/config/x"));
+
+        assert!(evaluator.can_read("bob", "anything"));
+        assert!(evaluator.can_write("bob", "/ops/x"));
+        assert!(evaluator.can_write("bob", "/zzz"));
+        assert!(!evaluator.can_write("bob", "/config"));
+
+        assert!(evaluator.can_write("root", "anything"));
+        assert!(!evaluator.can_read("nobody", "/app/a"));
+    }
+
+    #[test]
+    fn test_audit() {
+        let findings = evaluator().audit();
+
+        assert!(findings.contains(&AuditFinding::RootUser("root".to_string())));
+        assert!(findings.contains(&AuditFinding::UnboundedWrite(grant(
+            "ops",
+            PermissionType::Write,
+            "/ops/",
+            "\0"
+        ))));
+        // bob reads everything through ops, and /app/ and /config through app
+        let overlaps = findings
+            .iter()
+            .filter(|finding| matches!(finding, AuditFinding::Overlapping { user, .. } if user == "bob"))
+            .count();
+        assert_eq!(overlaps, 2);
+        assert!(!findings.iter().any(
+            |finding| matches!(finding, AuditFinding::Overlapping { user, .. } if user == "alice")
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+//! Alarm monitoring and `NOSPACE` remediation.
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::ops::defrag::{rolling_defragment, RollingDefragReport};
+use crate::rpc::kv::CompactionOptions;
+use crate::rpc::maintenance::{
+    AlarmAction, AlarmMember, AlarmOptions, AlarmResponse, AlarmType, MaintenanceClient,
+};
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{interval, Interval};
+use tokio_stream::Stream;
+use tonic::Code;
+
+/// Transition of an alarm observed by [`AlarmMonitor`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlarmEvent {
+    /// The alarm has been raised on a member.
+    Raised(AlarmMember),
+    /// The alarm has been cleared from a member.
+    Cleared(AlarmMember),
+}
+
+impl AlarmEvent {
+    /// The member and alarm type of this transition.
+    #[inline]
+    pub fn alarm_member(&self) -> &AlarmMember {
+        match self {
+            AlarmEvent::Raised(alarm) | AlarmEvent::Cleared(alarm) => alarm,
+        }
+    }
+}
+
+type AlarmFuture = Pin<Box<dyn Future<Output = Result<AlarmResponse>> + Send>>;
+
+/// Stream of alarm transitions, produced by polling the active alarms of the cluster.
+///
+/// Alarms already active when the monitor starts are reported as raised by the first poll.
+pub struct AlarmMonitor {
+    maintenance: MaintenanceClient,
+    period: Duration,
+    /// created by the first poll, in the runtime
+    interval: Option<Interval>,
+    request: Option<AlarmFuture>,
+    active: Vec<AlarmMember>,
+    pending: VecDeque<AlarmEvent>,
+}
+
+impl Debug for AlarmMonitor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AlarmMonitor")
+            .field("period", &self.period)
+            .field("active", &self.active)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+impl AlarmMonitor {
+    /// Creates a monitor polling alarms every `period`, of at least a millisecond.
+    #[inline]
+    pub(crate) fn new(maintenance: MaintenanceClient, period: Duration) -> Self {
+        Self {
+            maintenance,
+            period: period.max(Duration::from_millis(1)),
+            interval: None,
+            request: None,
+            active: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Fetches the next alarm transition from this stream.
+    #[inline]
+    pub async fn message(&mut self) -> Result<Option<AlarmEvent>> {
+        use tokio_stream::StreamExt;
+        self.next().await.transpose()
+    }
+
+    /// Records the newly polled alarms, queueing their transitions.
+    fn update(&mut self, alarms: Vec<AlarmMember>) {
+        for alarm in &self.active {
+            if !alarms.contains(alarm) {
+                self.pending.push_back(AlarmEvent::Cleared(alarm.clone()));
+            }
+        }
+        for alarm in &alarms {
+            if !self.active.contains(alarm) {
+                self.pending.push_back(AlarmEvent::Raised(alarm.clone()));
+            }
+        }
+        self.active = alarms;
+    }
+}
+
+impl Stream for AlarmMonitor {
+    type Item = Result<AlarmEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            if this.request.is_none() {
+                let period = this.period;
+                let ticker = this.interval.get_or_insert_with(|| interval(period));
+                if ticker.poll_tick(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                let maintenance = this.maintenance.clone();
+                this.request = Some(Box::pin(async move {
+                    maintenance
+                        .alarm(AlarmAction::Get, AlarmType::None, None)
+                        .await
+                }));
+            }
+
+            let resp = match this.request.as_mut() {
+                Some(request) => match request.as_mut().poll(cx) {
+                    Poll::Ready(resp) => resp,
+                    Poll::Pending => return Poll::Pending,
+                },
+                None => continue,
+            };
+            this.request = None;
+
+            match resp {
+                Ok(resp) => this.update(resp.alarms().to_vec()),
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+/// Recovers from a `NOSPACE` alarm: compacts to the current revision, defragments
+/// every member, and disarms the `NOSPACE` alarms.
+pub(crate) async fn remediate_nospace(client: &Client) -> Result<RollingDefragReport> {
+    let revision = client
+        .status()
+        .await?
+        .header()
+        .map(|h| h.revision())
+        .unwrap_or_default();
+    match client
+        .compact(revision, Some(CompactionOptions::new().with_physical()))
+        .await
+    {
+        // already compacted at this revision
+        Err(Error::GRpcStatus(status)) if status.code() == Code::OutOfRange => {}
+        resp => {
+            resp?;
+        }
+    }
+
+    let report = rolling_defragment(client, None).await?;
+
+    let resp = client
+        .alarm(AlarmAction::Get, AlarmType::None, None)
+        .await?;
+    for alarm in resp.alarms() {
+        if alarm.alarm() == AlarmType::Nospace {
+            let mut options = AlarmOptions::new();
+            options.with_member(alarm.member_id());
+            client
+                .alarm(AlarmAction::Deactivate, AlarmType::Nospace, Some(options))
+                .await?;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::get_client;
+
+    #[tokio::test]
+    async fn test_alarm_monitor() -> Result<()> {
+        let client = get_client().await?;
+        let member_id = client.status().await?.header().unwrap().member_id();
+        let mut monitor = client.alarm_monitor(Duration::from_millis(100));
+
+        let mut options = AlarmOptions::new();
+        options.with_member(member_id);
+        client
+            .alarm(AlarmAction::Activate, AlarmType::Nospace, Some(options))
+            .await?;
+
+        match monitor.message().await? {
+            Some(AlarmEvent::Raised(alarm)) => {
+                assert_eq!(alarm.member_id(), member_id);
+                assert_eq!(alarm.alarm(), AlarmType::Nospace);
+            }
+            _ => panic!("expected a raised alarm"),
+        }
+
+        client.remediate_nospace().await?;
+
+        match monitor.message().await? {
+            Some(AlarmEvent::Cleared(alarm)) => {
+                assert_eq!(alarm.member_id(), member_id);
+                assert_eq!(alarm.alarm(), AlarmType::Nospace);
+            }
+            _ => panic!("expected a cleared alarm"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+//! Automatic periodic compaction.
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::rpc::kv::CompactionOptions;
+use crate::rpc::lease::{LeaseKeepAliveStream, LeaseKeeper};
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::future::pending;
+use std::time::Duration;
+use tokio::time::{interval, Instant};
+use tonic::Code;
+
+/// Interval between checks in revision mode, as etcd's own revision compactor.
+const REVISION_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Consecutive failed checks after which a compactor stops, by default.
+const MAX_FAILURES: u32 = 10;
+
+/// Message of etcd's `ErrCompacted`, sent with `Code::OutOfRange` like `ErrFutureRev`.
+const COMPACTED: &str = "etcdserver: mvcc: required revision has been compacted";
+
+/// Which history a [`Compactor`] retains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactionMode {
+    /// Retains the history of the given duration, by sampling revisions over time.
+    Periodic(Duration),
+    /// Retains the given number of latest revisions.
+    Revision(i64),
+}
+
+/// Options for [`Compactor`].
+#[derive(Debug, Default, Clone)]
+pub struct CompactorOptions {
+    check_interval: Option<Duration>,
+    election: Option<(Vec<u8>, i64)>,
+    max_failures: Option<u32>,
+}
+
+impl CompactorOptions {
+    /// Creates a `CompactorOptions`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            check_interval: None,
+            election: None,
+            max_failures: None,
+        }
+    }
+
+    /// Interval between revision samples and compaction attempts.
+    ///
+    /// Defaults to a tenth of the retention in periodic mode and 5 minutes in revision mode.
+    #[inline]
+    pub const fn with_check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = Some(interval);
+        self
+    }
+
+    /// Only compacts while leading the election `name`, campaigning with a lease
+    /// of `ttl` seconds, so that only one of several compactors is active at a time.
+    ///
+    /// The lease is revoked once the compactor stops, resigning at once.
+    #[inline]
+    pub fn with_election(mut self, name: impl Into<Vec<u8>>, ttl: i64) -> Self {
+        self.election = Some((name.into(), ttl));
+        self
+    }
+
+    /// Stops the compactor with the error of the last check once `max_failures` checks
+    /// in a row have failed. Defaults to 10.
+    #[inline]
+    pub const fn with_max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = Some(max_failures);
+        self
+    }
+}
+
+/// Compacts the key-value store history according to a [`CompactionMode`].
+pub struct Compactor {
+    client: Client,
+    mode: CompactionMode,
+    options: CompactorOptions,
+    samples: VecDeque<(Instant, i64)>,
+    compacted: i64,
+}
+
+impl Compactor {
+    /// Creates a compactor.
+    ///
+    /// Fails if the mode retains no history at all.
+    pub fn new(
+        client: &Client,
+        mode: CompactionMode,
+        options: Option<CompactorOptions>,
+    ) -> Result<Self> {
+        check_mode(mode)?;
+
+        Ok(Self {
+            client: client.clone(),
+            mode,
+            options: options.unwrap_or_default(),
+            samples: VecDeque::new(),
+            compacted: 0,
+        })
+    }
+
+    /// Runs the compactor until the election lease is lost, the client is closed or too many
+    /// checks fail in a row, see [`CompactorOptions::with_max_failures`]. Failed checks are
+    /// retried on the next tick, like etcd's own compactor.
+    /// The lease is kept alive during compactions, which are aborted once it is lost.
+    ///
+    /// The returned future is meant to be spawned as a background task. The election
+    /// lease is revoked when it returns, or in the background when it is dropped.
+    pub async fn run(&mut self) -> Result<()> {
+        let election = match self.options.election.clone() {
+            Some((name, ttl)) => {
+                let lease = self.client.lease_grant(ttl, None).await?.id();
+                Some((name, LeaseGuard::new(&self.client, lease), ttl))
+            }
+            None => None,
+        };
+
+        match election {
+            Some((name, guard, ttl)) => {
+                let resp = self.compact(Some((name, guard.lease, ttl))).await;
+                guard.revoke().await;
+                resp
+            }
+            None => self.compact(None).await,
+        }
+    }
+
+    /// Compacts periodically, while leading the election if given with its lease.
+    async fn compact(&mut self, election: Option<(Vec<u8>, i64, i64)>) -> Result<()> {
+        let check_interval = self.options.check_interval.unwrap_or(match self.mode {
+            CompactionMode::Periodic(retention) => retention / 10,
+            CompactionMode::Revision(_) => REVISION_CHECK_INTERVAL,
+        });
+
+        let lease = match election {
+            Some((name, lease, ttl)) => Some((self.campaign(name, lease, ttl).await?, ttl)),
+            None => None,
+        };
+
+        // the lease is kept alive while checking, and losing it aborts the check
+        let keeping = async move {
+            match lease {
+                Some(((keeper, stream), ttl)) => keep_lease_alive(keeper, stream, ttl).await,
+                None => pending().await,
+            }
+        };
+        tokio::pin!(keeping);
+
+        let max_failures = self.options.max_failures.unwrap_or(MAX_FAILURES);
+        let mut failures = 0;
+        let mut ticker = interval(check_interval.max(Duration::from_millis(1)));
+        loop {
+            tokio::select! {
+                e = &mut keeping => return Err(e),
+                resp = async {
+                    ticker.tick().await;
+                    self.check().await
+                } => match resp {
+                    Ok(()) => failures = 0,
+                    Err(e) => {
+                        failures += 1;
+                        if self.client.is_closed() || failures >= max_failures {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Campaigns for leadership, keeping the campaign lease alive meanwhile.
+    async fn campaign(
+        &self,
+        name: Vec<u8>,
+        lease: i64,
+        ttl: i64,
+    ) -> Result<(LeaseKeeper, LeaseKeepAliveStream)> {
+        let (mut keeper, mut stream) = self.client.lease_keep_alive(lease).await?;
+
+        let campaign = self.client.campaign(name, format!("{:x}", lease), lease);
+        tokio::pin!(campaign);
+        let mut ticker = interval(keep_alive_interval(ttl));
+        loop {
+            tokio::select! {
+                resp = &mut campaign => {
+                    resp?;
+                    return Ok((keeper, stream));
+                }
+                _ = ticker.tick() => keep_alive(&mut keeper, &mut stream).await?,
+            }
+        }
+    }
+
+    /// Samples the current revision and compacts if the retention allows it.
+    async fn check(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let revision = self
+            .client
+            .status()
+            .await?
+            .header()
+            .map(|h| h.revision())
+            .unwrap_or_default();
+
+        let target = match self.mode {
+            CompactionMode::Revision(retain) => revision - retain,
+            CompactionMode::Periodic(retention) => {
+                self.samples.push_back((now, revision));
+                // keep the newest sample that is older than the retention
+                while self.samples.len() > 1 && now - self.samples[1].0 >= retention {
+                    self.samples.pop_front();
+                }
+                match self.samples.front() {
+                    Some((at, revision)) if now - *at >= retention => *revision,
+                    _ => 0,
+                }
+            }
+        };
+
+        if target <= self.compacted {
+            return Ok(());
+        }
+
+        match self
+            .client
+            .compact(target, Some(CompactionOptions::new().with_physical()))
+            .await
+        {
+            // compacted by someone else in the meantime
+            Err(Error::GRpcStatus(status))
+                if status.code() == Code::OutOfRange && status.message() == COMPACTED => {}
+            resp => {
+                resp?;
+            }
+        }
+        self.compacted = target;
+        Ok(())
+    }
+}
+
+impl Debug for Compactor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Compactor")
+            .field("mode", &self.mode)
+            .field("options", &self.options)
+            .field("compacted", &self.compacted)
+            .finish()
+    }
+}
+
+/// Checks that `mode` retains some history.
+fn check_mode(mode: CompactionMode) -> Result<()> {
+    match mode {
+        CompactionMode::Periodic(retention) if retention.is_zero() => Err(Error::InvalidArgs(
+            String::from("compaction retention must not be zero"),
+        )),
+        CompactionMode::Revision(retain) if retain <= 0 => Err(Error::InvalidArgs(String::from(
+            "compaction must retain a positive number of revisions",
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Revokes the election lease of a compactor once it stops, best-effort.
+struct LeaseGuard {
+    client: Client,
+    lease: i64,
+    revoked: bool,
+}
+
+impl LeaseGuard {
+    #[inline]
+    fn new(client: &Client, lease: i64) -> Self {
+        Self {
+            client: client.clone(),
+            lease,
+            revoked: false,
+        }
+    }
+
+    /// Revokes the lease, ignoring errors since it expires anyway.
+    async fn revoke(mut self) {
+        self.revoked = true;
+        let _ = self.client.lease_revoke(self.lease).await;
+    }
+}
+
+impl Drop for LeaseGuard {
+    fn drop(&mut self) {
+        if self.revoked {
+            return;
+        }
+        // the future of the compactor is dropped
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            let lease = self.lease;
+            runtime.spawn(async move {
+                let _ = client.lease_revoke(lease).await;
+            });
+        }
+    }
+}
+
+/// Interval between keep alive requests of a lease of `ttl` seconds.
+#[inline]
+fn keep_alive_interval(ttl: i64) -> Duration {
+    Duration::from_millis((ttl.max(1) as u64) * 1000 / 3)
+}
+
+/// Keeps the lease alive until it expires, returning the error that ended it.
+async fn keep_lease_alive(
+    mut keeper: LeaseKeeper,
+    mut stream: LeaseKeepAliveStream,
+    ttl: i64,
+) -> Error {
+    let mut ticker = interval(keep_alive_interval(ttl));
+    loop {
+        ticker.tick().await;
+        if let Err(e) = keep_alive(&mut keeper, &mut stream).await {
+            return e;
+        }
+    }
+}
+
+/// Keeps the lease alive, failing if it has expired.
+async fn keep_alive(keeper: &mut LeaseKeeper, stream: &mut LeaseKeepAliveStream) -> Result<()> {
+    keeper.keep_alive().await?;
+    match stream.message().await? {
+        Some(resp) if resp.ttl() > 0 => Ok(()),
+        _ => Err(Error::ElectError(
+            "compactor election lease expired".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::get_client;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_revision_compactor() -> Result<()> {
+        let client = get_client().await?;
+        let old = client.put("compactor", "old", None).await?;
+        let old_revision = old.header().unwrap().revision();
+        client.put("compactor", "new", None).await?;
+
+        let options = CompactorOptions::new()
+            .with_check_interval(Duration::from_millis(100))
+            .with_election("compactor-test", 2);
+        let mut compactor = Compactor::new(&client, CompactionMode::Revision(1), Some(options))?;
+        assert!(timeout(Duration::from_secs(1), compactor.run())
+            .await
+            .is_err());
+        // the dropped compactor resigns by revoking its lease
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(client.leader("compactor-test").await.is_err());
+
+        let resp = client
+            .get(
+                "compactor",
+                Some(crate::GetOptions::new().with_revision(old_revision)),
+            )
+            .await;
+        assert!(resp.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_compaction_mode() {
+        assert!(check_mode(CompactionMode::Revision(-1)).is_err());
+        assert!(check_mode(CompactionMode::Revision(0)).is_err());
+        assert!(check_mode(CompactionMode::Revision(1)).is_ok());
+        assert!(check_mode(CompactionMode::Periodic(Duration::ZERO)).is_err());
+        assert!(check_mode(CompactionMode::Periodic(Duration::from_secs(1))).is_ok());
+    }
+}
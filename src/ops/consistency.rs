@@ -0,0 +1,288 @@
+//! Cluster-wide consistency check using `HashKV`.
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::rpc::maintenance::{AlarmAction, AlarmOptions, AlarmType, HashKvResponse};
+use std::collections::HashMap;
+
+/// Options for `check_consistency` operation.
+#[derive(Debug, Default, Clone)]
+pub struct ConsistencyCheckOptions {
+    revision: i64,
+    raise_alarm: bool,
+}
+
+impl ConsistencyCheckOptions {
+    /// Creates a `ConsistencyCheckOptions`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            revision: 0,
+            raise_alarm: false,
+        }
+    }
+
+    /// Hashes the keyspace at `revision` instead of the lowest current revision
+    /// of the members.
+    #[inline]
+    pub const fn with_revision(mut self, revision: i64) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    /// Raises a `CORRUPT` alarm for every mismatching member.
+    ///
+    /// No alarm is raised for inconclusive members.
+    #[inline]
+    pub const fn with_alarm(mut self) -> Self {
+        self.raise_alarm = true;
+        self
+    }
+}
+
+/// Report of a cluster-wide consistency check.
+#[derive(Debug)]
+pub struct ConsistencyReport {
+    revision: i64,
+    hashes: HashMap<u64, Result<HashKvResponse>>,
+    mismatched: Vec<u64>,
+    inconclusive: Vec<u64>,
+}
+
+impl ConsistencyReport {
+    /// The revision the keyspace was hashed at.
+    #[inline]
+    pub const fn revision(&self) -> i64 {
+        self.revision
+    }
+
+    /// The `HashKV` result of every member, keyed by member ID.
+    #[inline]
+    pub fn hashes(&self) -> &HashMap<u64, Result<HashKvResponse>> {
+        &self.hashes
+    }
+
+    /// Members whose hash differs from the hash of a strict majority of the members
+    /// at the same compact revision.
+    #[inline]
+    pub fn mismatched(&self) -> &[u64] {
+        &self.mismatched
+    }
+
+    /// Members at a compact revision where the hashes differ but none has a strict
+    /// majority, so that the diverged members cannot be told apart.
+    #[inline]
+    pub fn inconclusive(&self) -> &[u64] {
+        &self.inconclusive
+    }
+
+    /// Whether the hashes of all members at the same compact revision are equal.
+    ///
+    /// Members that could not be queried are not taken into account.
+    #[inline]
+    pub fn is_consistent(&self) -> bool {
+        self.mismatched.is_empty() && self.inconclusive.is_empty()
+    }
+}
+
+/// Members whose hash differs from the other members, and members whose group
+/// has no majority hash.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Mismatches {
+    mismatched: Vec<u64>,
+    inconclusive: Vec<u64>,
+}
+
+/// Finds members whose hash differs from the hash of a strict majority of members
+/// at the same compact revision. Hashes at different compact revisions are not
+/// comparable. If no hash has a strict majority, the diverged members cannot be
+/// told apart and the whole group is reported as inconclusive instead.
+///
+/// `hashes` yields the member ID, compact revision and hash of each member.
+fn find_mismatched(hashes: impl IntoIterator<Item = (u64, i64, u32)>) -> Mismatches {
+    let mut groups: HashMap<i64, Vec<(u64, u32)>> = HashMap::new();
+    for (id, compact_revision, hash) in hashes {
+        groups.entry(compact_revision).or_default().push((id, hash));
+    }
+
+    let mut found = Mismatches::default();
+    for members in groups.values() {
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for (_, hash) in members {
+            *counts.entry(*hash).or_default() += 1;
+        }
+        if counts.len() == 1 {
+            continue;
+        }
+
+        match counts.iter().max_by_key(|(_, n)| **n) {
+            Some((expected, max)) if max * 2 > members.len() => found.mismatched.extend(
+                members
+                    .iter()
+                    .filter(|(_, hash)| hash != expected)
+                    .map(|(id, _)| *id),
+            ),
+            _ => found.inconclusive.extend(members.iter().map(|(id, _)| *id)),
+        }
+    }
+
+    found.mismatched.sort_unstable();
+    found.inconclusive.sort_unstable();
+    found
+}
+
+/// Hashes the keyspace of every member at the same revision and compares the results.
+pub(crate) async fn check_consistency(
+    client: &Client,
+    options: Option<ConsistencyCheckOptions>,
+) -> Result<ConsistencyReport> {
+    let options = options.unwrap_or_default();
+    let resp = client.member_list().await?;
+    let mut hashes = HashMap::with_capacity(resp.members().len());
+    let mut members = Vec::with_capacity(resp.members().len());
+    let mut revisions = Vec::with_capacity(resp.members().len());
+    for member in resp.members() {
+        let maintenance = match client.member_maintenance_client(member).await {
+            Ok(maintenance) => maintenance,
+            Err(e) => {
+                hashes.insert(member.id(), Err(e));
+                continue;
+            }
+        };
+        if options.revision <= 0 {
+            match maintenance.status().await {
+                Ok(status) => revisions.extend(status.header().map(|h| h.revision())),
+                Err(e) => {
+                    hashes.insert(member.id(), Err(e));
+                    continue;
+                }
+            }
+        }
+        members.push((member.id(), maintenance));
+    }
+
+    // the lowest revision, which all members have reached, like etcd's corruption check
+    let revision = if options.revision > 0 {
+        options.revision
+    } else {
+        revisions.into_iter().min().unwrap_or_default()
+    };
+    for (id, maintenance) in members {
+        hashes.insert(id, maintenance.hash_kv(revision).await);
+    }
+
+    let Mismatches {
+        mismatched,
+        inconclusive,
+    } = find_mismatched(hashes.iter().filter_map(|(id, resp)| {
+        resp.as_ref()
+            .ok()
+            .map(|resp| (*id, resp.compact_version(), resp.hash()))
+    }));
+    if options.raise_alarm {
+        let maintenance = client.maintenance_client();
+        for id in &mismatched {
+            let mut alarm_options = AlarmOptions::new();
+            alarm_options.with_member(*id);
+            maintenance
+                .alarm(
+                    AlarmAction::Activate,
+                    AlarmType::Corrupt,
+                    Some(alarm_options),
+                )
+                .await?;
+        }
+    }
+
+    Ok(ConsistencyReport {
+        revision,
+        hashes,
+        mismatched,
+        inconclusive,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mismatched members only.
+    fn mismatched(ids: Vec<u64>) -> Mismatches {
+        Mismatches {
+            mismatched: ids,
+            inconclusive: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_mismatched() {
+        // all equal
+        assert_eq!(
+            find_mismatched(vec![(1, 5, 10), (2, 5, 10), (3, 5, 10)]),
+            Mismatches::default()
+        );
+
+        // one diverged member
+        assert_eq!(
+            find_mismatched(vec![(1, 5, 10), (2, 5, 11), (3, 5, 10)]),
+            mismatched(vec![2])
+        );
+
+        // different compact revisions are not comparable
+        assert_eq!(
+            find_mismatched(vec![(1, 5, 10), (2, 6, 11), (3, 5, 10)]),
+            Mismatches::default()
+        );
+    }
+
+    #[test]
+    fn test_find_mismatched_tie() {
+        // 1-1 tie
+        assert_eq!(
+            find_mismatched(vec![(1, 5, 10), (2, 5, 11)]),
+            Mismatches {
+                mismatched: Vec::new(),
+                inconclusive: vec![1, 2],
+            }
+        );
+
+        // 2-2-1 tie
+        assert_eq!(
+            find_mismatched(vec![
+                (1, 5, 10),
+                (2, 5, 11),
+                (3, 5, 10),
+                (4, 5, 11),
+                (5, 5, 12)
+            ]),
+            Mismatches {
+                mismatched: Vec::new(),
+                inconclusive: vec![1, 2, 3, 4, 5],
+            }
+        );
+
+        // a plurality without a strict majority is inconclusive too
+        assert_eq!(
+            find_mismatched(vec![(1, 5, 10), (2, 5, 10), (3, 5, 11), (4, 5, 12)]),
+            Mismatches {
+                mismatched: Vec::new(),
+                inconclusive: vec![1, 2, 3, 4],
+            }
+        );
+
+        // only the group without a majority is inconclusive
+        assert_eq!(
+            find_mismatched(vec![
+                (1, 5, 10),
+                (2, 5, 11),
+                (3, 6, 10),
+                (4, 6, 10),
+                (5, 6, 11)
+            ]),
+            Mismatches {
+                mismatched: vec![5],
+                inconclusive: vec![1, 2],
+            }
+        );
+    }
+}
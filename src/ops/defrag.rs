@@ -0,0 +1,212 @@
+//! Rolling online defragmentation.
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::rpc::cluster::Member;
+use crate::rpc::maintenance::{MaintenanceClient, StatusResponse};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// Options for `rolling_defragment` operation.
+#[derive(Debug, Clone)]
+pub struct RollingDefragOptions {
+    threshold: f64,
+    health_check_interval: Duration,
+    health_check_timeout: Duration,
+}
+
+impl RollingDefragOptions {
+    /// Creates a `RollingDefragOptions`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            threshold: 0.0,
+            health_check_interval: Duration::from_millis(500),
+            health_check_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Skips members whose fragmentation ratio, `(db_size - raft_used_db_size) / db_size`,
+    /// is below `threshold`. Defaults to 0, which defragments every member.
+    #[inline]
+    pub const fn with_fragmentation_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Interval between health checks after a member is defragmented. Defaults to 500ms.
+    #[inline]
+    pub const fn with_health_check_interval(mut self, interval: Duration) -> Self {
+        self.health_check_interval = interval;
+        self
+    }
+
+    /// How long to wait for a member to become healthy after it is defragmented
+    /// before aborting the rollout. Defaults to 30s.
+    #[inline]
+    pub const fn with_health_check_timeout(mut self, timeout: Duration) -> Self {
+        self.health_check_timeout = timeout;
+        self
+    }
+}
+
+impl Default for RollingDefragOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of defragmenting a single member.
+#[derive(Debug, Clone)]
+pub struct MemberDefragResult {
+    member_id: u64,
+    name: String,
+    skipped: bool,
+    db_size_before: i64,
+    db_size_after: i64,
+}
+
+impl MemberDefragResult {
+    /// Member id.
+    #[inline]
+    pub const fn member_id(&self) -> u64 {
+        self.member_id
+    }
+
+    /// The human-readable name of the member.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the member was skipped for being below the fragmentation threshold.
+    #[inline]
+    pub const fn skipped(&self) -> bool {
+        self.skipped
+    }
+
+    /// Size of db before defragmentation, in bytes.
+    #[inline]
+    pub const fn db_size_before(&self) -> i64 {
+        self.db_size_before
+    }
+
+    /// Size of db after defragmentation, in bytes.
+    #[inline]
+    pub const fn db_size_after(&self) -> i64 {
+        self.db_size_after
+    }
+
+    /// Space reclaimed by defragmentation, in bytes.
+    #[inline]
+    pub fn reclaimed(&self) -> i64 {
+        (self.db_size_before - self.db_size_after).max(0)
+    }
+}
+
+/// Report of a rolling defragmentation, with members in the order they were processed.
+#[derive(Debug, Clone, Default)]
+pub struct RollingDefragReport {
+    members: Vec<MemberDefragResult>,
+}
+
+impl RollingDefragReport {
+    /// Results of each member, followers first and the leader last.
+    #[inline]
+    pub fn members(&self) -> &[MemberDefragResult] {
+        &self.members
+    }
+
+    /// Total space reclaimed across the cluster, in bytes.
+    #[inline]
+    pub fn reclaimed(&self) -> i64 {
+        self.members.iter().map(MemberDefragResult::reclaimed).sum()
+    }
+}
+
+/// Fragmentation ratio of a member's backend database.
+#[inline]
+fn fragmentation(status: &StatusResponse) -> f64 {
+    if status.db_size() <= 0 {
+        return 0.0;
+    }
+    (status.db_size() - status.raft_used_db_size()) as f64 / status.db_size() as f64
+}
+
+/// Waits until the member reports no errors and sees a leader.
+async fn wait_healthy(
+    maintenance: &MaintenanceClient,
+    member_id: u64,
+    options: &RollingDefragOptions,
+) -> Result<StatusResponse> {
+    let deadline = Instant::now() + options.health_check_timeout;
+    loop {
+        match maintenance.status().await {
+            Ok(status) if status.errors().is_empty() && status.leader() != 0 => return Ok(status),
+            _ if Instant::now() >= deadline => {
+                return Err(Error::DefragError(format!(
+                    "member {:x} is not healthy after defragmentation",
+                    member_id
+                )));
+            }
+            _ => sleep(options.health_check_interval).await,
+        }
+    }
+}
+
+/// Defragments members one at a time, followers first and then the leader.
+pub(crate) async fn rolling_defragment(
+    client: &Client,
+    options: Option<RollingDefragOptions>,
+) -> Result<RollingDefragReport> {
+    let options = options.unwrap_or_default();
+    let leader = client.status().await?.leader();
+
+    let resp = client.member_list().await?;
+    let mut members = resp.members().iter().collect::<Vec<_>>();
+    // stable sort keeps member_list order among followers
+    members.sort_by_key(|member| member.id() == leader);
+
+    let mut report = RollingDefragReport::default();
+    for member in members {
+        match defragment_member(client, member, &options).await {
+            Ok(result) => report.members.push(result),
+            Err(error) => {
+                return Err(Error::RollingDefragError {
+                    report: Box::new(report),
+                    error: Box::new(error),
+                })
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Defragments `member` unless it is below the fragmentation threshold.
+async fn defragment_member(
+    client: &Client,
+    member: &Member,
+    options: &RollingDefragOptions,
+) -> Result<MemberDefragResult> {
+    let maintenance = client.member_maintenance_client(member).await?;
+    let before = maintenance.status().await?;
+
+    let mut result = MemberDefragResult {
+        member_id: member.id(),
+        name: member.name().to_string(),
+        skipped: false,
+        db_size_before: before.db_size(),
+        db_size_after: before.db_size(),
+    };
+
+    if fragmentation(&before) < options.threshold {
+        result.skipped = true;
+    } else {
+        maintenance.defragment().await?;
+        let after = wait_healthy(&maintenance, member.id(), options).await?;
+        result.db_size_after = after.db_size();
+    }
+    Ok(result)
+}
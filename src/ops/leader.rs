@@ -0,0 +1,137 @@
+//! Leader transfer.
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// Options for `transfer_leader` operation.
+#[derive(Debug, Clone)]
+pub struct TransferLeaderOptions {
+    target: Option<u64>,
+    timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl TransferLeaderOptions {
+    /// Creates a `TransferLeaderOptions`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            target: None,
+            timeout: Duration::from_secs(30),
+            poll_interval: Duration::from_millis(200),
+        }
+    }
+
+    /// Transfers leadership to the member `target` instead of the most up-to-date voting member.
+    #[inline]
+    pub const fn with_target(mut self, target: u64) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// How long to wait for the new leader to be confirmed. Defaults to 30s.
+    #[inline]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Interval between checks of the new leader. Defaults to 200ms.
+    #[inline]
+    pub const fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+impl Default for TransferLeaderOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Moves the leadership away from the current leader, returning the new leader ID.
+pub(crate) async fn transfer_leader(
+    client: &Client,
+    options: Option<TransferLeaderOptions>,
+) -> Result<u64> {
+    let options = options.unwrap_or_default();
+    let leader_id = client.status().await?.leader();
+
+    let resp = client.member_list().await?;
+    let members = resp.members();
+    let leader = members
+        .iter()
+        .find(|member| member.id() == leader_id)
+        .ok_or_else(|| Error::LeaderTransferError("no leader found".to_string()))?;
+
+    let target = match options.target {
+        Some(id) => {
+            let target = members
+                .iter()
+                .find(|member| member.id() == id)
+                .ok_or_else(|| Error::LeaderTransferError(format!("member {:x} not found", id)))?;
+            if target.is_learner() {
+                return Err(Error::LeaderTransferError(format!(
+                    "member {:x} is a learner",
+                    id
+                )));
+            }
+            if id == leader_id {
+                return Ok(leader_id);
+            }
+            target
+        }
+        None => {
+            // the voting member with the highest applied index
+            let mut best = None;
+            for member in members
+                .iter()
+                .filter(|member| !member.is_learner() && member.id() != leader_id)
+            {
+                let status = match client.member_maintenance_client(member).await {
+                    Ok(maintenance) => maintenance.status().await,
+                    Err(e) => Err(e),
+                };
+                if let Ok(status) = status {
+                    let applied = status.raft_applied_index();
+                    match best {
+                        Some((_, best_applied)) if best_applied >= applied => {}
+                        _ => best = Some((member, applied)),
+                    }
+                }
+            }
+            best.map(|(member, _)| member).ok_or_else(|| {
+                Error::LeaderTransferError("no reachable voting member to transfer to".to_string())
+            })?
+        }
+    };
+
+    // MoveLeader must be served by the leader
+    client
+        .member_maintenance_client(leader)
+        .await?
+        .move_leader(target.id())
+        .await?;
+
+    let maintenance = client.member_maintenance_client(target).await?;
+    let deadline = Instant::now() + options.timeout;
+    loop {
+        if let Ok(status) = maintenance.status().await {
+            if status.leader() == target.id() {
+                return Ok(target.id());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Err(Error::LeaderTransferError(format!(
+                "member {:x} did not become the leader in time",
+                target.id()
+            )));
+        }
+        sleep(options.poll_interval).await;
+    }
+}
@@ -0,0 +1,244 @@
+//! Safe cluster membership changes.
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::rpc::cluster::{Member, MemberAddOptions};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// Options for membership change workflows.
+#[derive(Debug, Clone)]
+pub struct MembershipOptions {
+    max_lag: u64,
+    catch_up_timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl MembershipOptions {
+    /// Creates a `MembershipOptions`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            max_lag: 100,
+            catch_up_timeout: Duration::from_secs(5 * 60),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Considers a learner caught up when its applied index is at most `max_lag`
+    /// entries behind the leader's. Defaults to 100.
+    #[inline]
+    pub const fn with_max_lag(mut self, max_lag: u64) -> Self {
+        self.max_lag = max_lag;
+        self
+    }
+
+    /// How long to wait for a new learner to start and catch up with the leader
+    /// before giving up. Defaults to 5 minutes.
+    #[inline]
+    pub const fn with_catch_up_timeout(mut self, timeout: Duration) -> Self {
+        self.catch_up_timeout = timeout;
+        self
+    }
+
+    /// Interval between checks of the learner progress. Defaults to 1s.
+    #[inline]
+    pub const fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+impl Default for MembershipOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a cluster of `voters` voting members keeps quorum with `healthy` healthy voters.
+#[inline]
+fn has_quorum(voters: usize, healthy: usize) -> bool {
+    healthy > voters / 2
+}
+
+/// Checks that removing the member `id` keeps quorum among the remaining healthy voters.
+async fn check_removal(client: &Client, id: u64) -> Result<()> {
+    let resp = client.member_list().await?;
+    let member = resp
+        .members()
+        .iter()
+        .find(|member| member.id() == id)
+        .ok_or_else(|| Error::MembershipError(format!("member {:x} not found", id)))?;
+    if member.is_learner() {
+        // learners do not vote
+        return Ok(());
+    }
+
+    let voters = resp
+        .members()
+        .iter()
+        .filter(|member| !member.is_learner())
+        .collect::<Vec<_>>();
+    let mut healthy = 0;
+    for member in voters.iter().filter(|member| member.id() != id) {
+        if let Ok(maintenance) = client.member_maintenance_client(member).await {
+            if let Ok(status) = maintenance.status().await {
+                if status.errors().is_empty() {
+                    healthy += 1;
+                }
+            }
+        }
+    }
+
+    if !has_quorum(voters.len() - 1, healthy) {
+        return Err(Error::MembershipError(format!(
+            "removing member {:x} would lose quorum: {} of {} remaining voters are healthy",
+            id,
+            healthy,
+            voters.len() - 1
+        )));
+    }
+    Ok(())
+}
+
+/// Waits for the learner `id` to start and catch up with the leader.
+async fn wait_caught_up(client: &Client, id: u64, options: &MembershipOptions) -> Result<()> {
+    let deadline = Instant::now() + options.catch_up_timeout;
+    loop {
+        if caught_up(client, id, options.max_lag).await? {
+            return Ok(());
+        }
+
+        if Instant::now() >= deadline {
+            return Err(Error::MembershipError(format!(
+                "learner {:x} did not catch up with the leader in time",
+                id
+            )));
+        }
+        sleep(options.poll_interval).await;
+    }
+}
+
+/// Whether the learner `id` has started and caught up with the leader.
+async fn caught_up(client: &Client, id: u64, max_lag: u64) -> Result<bool> {
+    let resp = client.member_list().await?;
+    let learner = match resp.members().iter().find(|member| member.id() == id) {
+        Some(member) => member,
+        None => return Err(Error::MembershipError(format!("member {:x} not found", id))),
+    };
+    // the client URLs are published once the learner has started
+    if learner.client_urls().is_empty() {
+        return Ok(false);
+    }
+
+    let learner_status = match client.member_maintenance_client(learner).await {
+        Ok(maintenance) => match maintenance.status().await {
+            Ok(status) => status,
+            Err(_) => return Ok(false),
+        },
+        Err(_) => return Ok(false),
+    };
+    let leader = match resp
+        .members()
+        .iter()
+        .find(|member| member.id() == learner_status.leader())
+    {
+        Some(leader) => leader,
+        None => return Ok(false),
+    };
+    let leader_status = client
+        .member_maintenance_client(leader)
+        .await?
+        .status()
+        .await?;
+
+    Ok(learner_status.raft_applied_index() + max_lag >= leader_status.raft_applied_index())
+}
+
+/// Adds a learner with `peer_urls`, waits until it catches up, and promotes it.
+pub(crate) async fn add_member<E: AsRef<str>, S: AsRef<[E]>>(
+    client: &Client,
+    peer_urls: S,
+    options: Option<MembershipOptions>,
+) -> Result<Member> {
+    let options = options.unwrap_or_default();
+    let resp = client
+        .member_add(peer_urls, Some(MemberAddOptions::new().with_is_learner()))
+        .await?;
+    let id = resp
+        .member()
+        .map(Member::id)
+        .ok_or_else(|| Error::MembershipError("added member is missing".to_string()))?;
+
+    let promoted = match wait_caught_up(client, id, &options).await {
+        Ok(()) => client.member_promote(id).await,
+        Err(e) => Err(e),
+    };
+    let resp = match promoted {
+        Ok(resp) => resp,
+        Err(e) => return Err(remove_learner(client, id, e).await),
+    };
+    resp.members()
+        .iter()
+        .find(|member| member.id() == id)
+        .cloned()
+        .ok_or_else(|| Error::MembershipError(format!("member {:x} not found", id)))
+}
+
+/// Removes the learner `id` that could not be promoted because of `error`,
+/// returning the error to report.
+async fn remove_learner(client: &Client, id: u64, error: Error) -> Error {
+    match client.member_remove(id).await {
+        Ok(_) => error,
+        Err(e) => Error::MembershipError(format!(
+            "learner {:x} was left in the cluster after {}: failed to remove it: {}",
+            id, error, e
+        )),
+    }
+}
+
+/// Replaces the member `old_id` by a new member with `peer_urls`,
+/// adding the new member before removing the old one.
+pub(crate) async fn replace_member<E: AsRef<str>, S: AsRef<[E]>>(
+    client: &Client,
+    old_id: u64,
+    peer_urls: S,
+    options: Option<MembershipOptions>,
+) -> Result<Member> {
+    // the removal is checked once the new member is a voter,
+    // so that the only member of a cluster can be replaced
+    let member = add_member(client, peer_urls, options).await?;
+    if let Err(e) = remove_member(client, old_id).await {
+        return Err(Error::MembershipError(format!(
+            "member {:x} was added, but member {:x} was not removed: {}",
+            member.id(),
+            old_id,
+            e
+        )));
+    }
+    Ok(member)
+}
+
+/// Removes the member `id`, refusing to if the cluster would lose quorum.
+pub(crate) async fn remove_member(client: &Client, id: u64) -> Result<()> {
+    check_removal(client, id).await?;
+    client.member_remove(id).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_quorum() {
+        assert!(has_quorum(1, 1));
+        assert!(!has_quorum(1, 0));
+        assert!(has_quorum(2, 2));
+        assert!(!has_quorum(2, 1));
+        assert!(has_quorum(3, 2));
+        assert!(!has_quorum(4, 2));
+        assert!(has_quorum(5, 3));
+    }
+}
@@ -0,0 +1,10 @@
+//! Cluster operations built on top of the etcd v3 API.
+
+pub mod access;
+pub mod alarm;
+pub mod compactor;
+pub mod consistency;
+pub mod defrag;
+pub mod leader;
+pub mod membership;
+pub mod rbac;
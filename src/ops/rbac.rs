@@ -0,0 +1,604 @@
+//! Declarative RBAC reconciliation.
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::rpc::auth::{Permission, PermissionType, RoleRevokePermissionOptions, UserAddOptions};
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+
+/// The root user and role are never deleted when pruning.
+const ROOT: &str = "root";
+
+/// Desired state of the users and roles of the cluster.
+///
+/// With the `serde` feature, it can be loaded from any serde format, e.g. JSON:
+///
+/// ```json
+/// {
+///     "roles": [
+///         { "name": "app", "permissions": [{ "type": "readwrite", "key": "/app/", "prefix": true }] }
+///     ],
+///     "users": [{ "name": "app", "password": "secret", "roles": ["app"] }]
+/// }
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone)]
+pub struct RbacConfig {
+    #[cfg_attr(feature = "serde", serde(default))]
+    roles: Vec<RoleSpec>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    users: Vec<UserSpec>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    prune: bool,
+}
+
+impl RbacConfig {
+    /// Creates an empty `RbacConfig`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            roles: Vec::new(),
+            users: Vec::new(),
+            prune: false,
+        }
+    }
+
+    /// Adds a desired role.
+    #[inline]
+    pub fn with_role(mut self, role: RoleSpec) -> Self {
+        self.roles.push(role);
+        self
+    }
+
+    /// Adds a desired user.
+    #[inline]
+    pub fn with_user(mut self, user: UserSpec) -> Self {
+        self.users.push(user);
+        self
+    }
+
+    /// Deletes the users and roles that are not described, except `root`.
+    #[inline]
+    pub const fn with_prune(mut self) -> Self {
+        self.prune = true;
+        self
+    }
+
+    /// The desired roles.
+    #[inline]
+    pub fn roles(&self) -> &[RoleSpec] {
+        &self.roles
+    }
+
+    /// The desired users.
+    #[inline]
+    pub fn users(&self) -> &[UserSpec] {
+        &self.users
+    }
+}
+
+/// Desired state of a role.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct RoleSpec {
+    name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    permissions: Vec<PermissionSpec>,
+}
+
+impl RoleSpec {
+    /// Creates a role without permissions.
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            permissions: Vec::new(),
+        }
+    }
+
+    /// Adds a permission to the role.
+    #[inline]
+    pub fn with_permission(mut self, permission: PermissionSpec) -> Self {
+        self.permissions.push(permission);
+        self
+    }
+
+    /// The role name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The permissions of the role.
+    #[inline]
+    pub fn permissions(&self) -> &[PermissionSpec] {
+        &self.permissions
+    }
+}
+
+/// Desired permission of a role.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct PermissionSpec {
+    #[cfg_attr(feature = "serde", serde(rename = "type", with = "perm_type_serde"))]
+    perm_type: PermissionType,
+    key: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    range_end: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    prefix: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    from_key: bool,
+}
+
+impl PermissionSpec {
+    /// Creates a permission on a single key.
+    #[inline]
+    pub fn new(perm_type: PermissionType, key: impl Into<String>) -> Self {
+        Self {
+            perm_type,
+            key: key.into(),
+            range_end: None,
+            prefix: false,
+            from_key: false,
+        }
+    }
+
+    /// Sets the range end of the permission.
+    #[inline]
+    pub fn with_range_end(mut self, range_end: impl Into<String>) -> Self {
+        self.range_end = Some(range_end.into());
+        self.prefix = false;
+        self.from_key = false;
+        self
+    }
+
+    /// Sets the permission on all keys prefixed with key.
+    #[inline]
+    pub fn with_prefix(mut self) -> Self {
+        self.range_end = None;
+        self.prefix = true;
+        self.from_key = false;
+        self
+    }
+
+    /// Sets the permission on all keys >= key.
+    #[inline]
+    pub fn with_from_key(mut self) -> Self {
+        self.range_end = None;
+        self.prefix = false;
+        self.from_key = true;
+        self
+    }
+
+    /// Converts to a [`Permission`].
+    pub fn to_permission(&self) -> Permission {
+        let perm = Permission::new(self.perm_type, self.key.as_bytes());
+        if self.prefix {
+            perm.with_prefix()
+        } else if self.from_key {
+            perm.with_from_key()
+        } else if let Some(range_end) = &self.range_end {
+            perm.with_range_end(range_end.as_bytes())
+        } else {
+            perm
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod perm_type_serde {
+    use super::PermissionType;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        perm_type: &PermissionType,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(match perm_type {
+            PermissionType::Read => "read",
+            PermissionType::Write => "write",
+            PermissionType::Readwrite => "readwrite",
+        })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<PermissionType, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match s.to_ascii_lowercase().as_str() {
+            "read" => Ok(PermissionType::Read),
+            "write" => Ok(PermissionType::Write),
+            "readwrite" => Ok(PermissionType::Readwrite),
+            _ => Err(D::Error::custom(format!("unknown permission type: {}", s))),
+        }
+    }
+}
+
+/// Desired state of a user.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone)]
+pub struct UserSpec {
+    name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    password: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    roles: Vec<String>,
+}
+
+impl UserSpec {
+    /// Creates a user without roles.
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            password: None,
+            roles: Vec::new(),
+        }
+    }
+
+    /// Sets the password used when the user is created.
+    /// Passwords of existing users are left untouched.
+    /// Users created without a password can only authenticate with TLS client certificates.
+    #[inline]
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Grants a role to the user.
+    #[inline]
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    /// The user name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The roles of the user.
+    #[inline]
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    }
+}
+
+impl Debug for UserSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserSpec")
+            .field("name", &self.name)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("roles", &self.roles)
+            .finish()
+    }
+}
+
+/// A single change of an [`RbacPlan`].
+#[derive(Clone)]
+pub enum RbacChange {
+    /// Adds a role.
+    AddRole(String),
+    /// Grants a permission to a role.
+    GrantPermission {
+        /// The role granted the permission.
+        role: String,
+        /// The granted permission.
+        permission: Permission,
+    },
+    /// Revokes a permission from a role.
+    RevokePermission {
+        /// The role the permission is revoked from.
+        role: String,
+        /// The revoked permission.
+        permission: Permission,
+    },
+    /// Adds a user.
+    AddUser {
+        /// The name of the user.
+        name: String,
+        /// The password of the user, `None` for a user without password.
+        password: Option<String>,
+    },
+    /// Deletes a user.
+    DeleteUser(String),
+    /// Grants a role to a user.
+    GrantRole {
+        /// The user granted the role.
+        user: String,
+        /// The granted role.
+        role: String,
+    },
+    /// Revokes a role from a user.
+    RevokeRole {
+        /// The user the role is revoked from.
+        user: String,
+        /// The revoked role.
+        role: String,
+    },
+    /// Deletes a role.
+    DeleteRole(String),
+}
+
+impl Display for RbacChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RbacChange::AddRole(role) => write!(f, "add role {}", role),
+            RbacChange::GrantPermission { role, permission } => write!(
+                f,
+                "grant {:?} [{}, {}) to role {}",
+                PermissionType::from(permission.get_type()),
+                String::from_utf8_lossy(permission.key()),
+                String::from_utf8_lossy(permission.range_end()),
+                role
+            ),
+            RbacChange::RevokePermission { role, permission } => write!(
+                f,
+                "revoke {:?} [{}, {}) from role {}",
+                PermissionType::from(permission.get_type()),
+                String::from_utf8_lossy(permission.key()),
+                String::from_utf8_lossy(permission.range_end()),
+                role
+            ),
+            RbacChange::AddUser { name, .. } => write!(f, "add user {}", name),
+            RbacChange::DeleteUser(name) => write!(f, "delete user {}", name),
+            RbacChange::GrantRole { user, role } => {
+                write!(f, "grant role {} to user {}", role, user)
+            }
+            RbacChange::RevokeRole { user, role } => {
+                write!(f, "revoke role {} from user {}", role, user)
+            }
+            RbacChange::DeleteRole(role) => write!(f, "delete role {}", role),
+        }
+    }
+}
+
+impl Debug for RbacChange {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // passwords are never printed
+        Display::fmt(self, f)
+    }
+}
+
+/// Changes bringing the cluster to an [`RbacConfig`], in the order they are applied.
+#[derive(Debug, Clone, Default)]
+pub struct RbacPlan {
+    changes: Vec<RbacChange>,
+}
+
+impl RbacPlan {
+    /// The changes of the plan.
+    #[inline]
+    pub fn changes(&self) -> &[RbacChange] {
+        &self.changes
+    }
+
+    /// Whether the cluster already matches the desired state.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Diffs `config` against the users and roles of the cluster.
+pub(crate) async fn plan_rbac(client: &Client, config: &RbacConfig) -> Result<RbacPlan> {
+    let auth = client.auth_client();
+    let existing_roles = auth.role_list().await?.roles().to_vec();
+    let existing_users = auth.user_list().await?.users().to_vec();
+
+    let mut role_changes = Vec::new();
+    for role in &config.roles {
+        let current = if existing_roles.contains(&role.name) {
+            auth.role_get(role.name.as_str()).await?.permissions()
+        } else {
+            role_changes.push(RbacChange::AddRole(role.name.clone()));
+            Vec::new()
+        };
+        let desired = role
+            .permissions
+            .iter()
+            .map(PermissionSpec::to_permission)
+            .collect::<Vec<_>>();
+
+        let current_ranges = current
+            .iter()
+            .map(Permission::normalized)
+            .collect::<HashSet<_>>();
+        let desired_ranges = desired
+            .iter()
+            .map(Permission::normalized)
+            .collect::<HashSet<_>>();
+        for permission in current {
+            if !desired_ranges.contains(&permission.normalized()) {
+                role_changes.push(RbacChange::RevokePermission {
+                    role: role.name.clone(),
+                    permission,
+                });
+            }
+        }
+        let mut granted = HashSet::new();
+        for permission in desired {
+            let range = permission.normalized();
+            if !current_ranges.contains(&range) && granted.insert(range) {
+                role_changes.push(RbacChange::GrantPermission {
+                    role: role.name.clone(),
+                    permission,
+                });
+            }
+        }
+    }
+
+    let mut user_changes = Vec::new();
+    let mut grant_changes = Vec::new();
+    for user in &config.users {
+        let current = if existing_users.contains(&user.name) {
+            auth.user_get(user.name.as_str()).await?.roles().to_vec()
+        } else {
+            user_changes.push(RbacChange::AddUser {
+                name: user.name.clone(),
+                password: user.password.clone(),
+            });
+            Vec::new()
+        };
+
+        for role in &current {
+            if !user.roles.contains(role) {
+                grant_changes.push(RbacChange::RevokeRole {
+                    user: user.name.clone(),
+                    role: role.clone(),
+                });
+            }
+        }
+        let mut granted = HashSet::new();
+        for role in &user.roles {
+            if !current.contains(role) && granted.insert(role) {
+                grant_changes.push(RbacChange::GrantRole {
+                    user: user.name.clone(),
+                    role: role.clone(),
+                });
+            }
+        }
+    }
+
+    let mut prune_changes = Vec::new();
+    if config.prune {
+        for name in &existing_users {
+            if name != ROOT && !config.users.iter().any(|user| &user.name == name) {
+                user_changes.push(RbacChange::DeleteUser(name.clone()));
+            }
+        }
+        for name in &existing_roles {
+            if name != ROOT && !config.roles.iter().any(|role| &role.name == name) {
+                prune_changes.push(RbacChange::DeleteRole(name.clone()));
+            }
+        }
+    }
+
+    let mut changes = role_changes;
+    changes.extend(user_changes);
+    changes.extend(grant_changes);
+    changes.extend(prune_changes);
+    Ok(RbacPlan { changes })
+}
+
+/// Applies the changes of `plan` in order.
+pub(crate) async fn apply_rbac(client: &Client, plan: &RbacPlan) -> Result<()> {
+    let auth = client.auth_client();
+    for change in &plan.changes {
+        match change {
+            RbacChange::AddRole(role) => {
+                auth.role_add(role.as_str()).await?;
+            }
+            RbacChange::GrantPermission { role, permission } => {
+                auth.role_grant_permission(role.as_str(), permission.clone())
+                    .await?;
+            }
+            RbacChange::RevokePermission { role, permission } => {
+                let (_, key, range_end) = permission.normalized();
+                let options = RoleRevokePermissionOptions::new().with_range_end(range_end);
+                auth.role_revoke_permission(role.as_str(), key, Some(options))
+                    .await?;
+            }
+            RbacChange::AddUser { name, password } => {
+                match password {
+                    Some(password) => auth.user_add(name.as_str(), password.as_str(), None),
+                    None => {
+                        auth.user_add(name.as_str(), "", Some(UserAddOptions::new().with_no_pwd()))
+                    }
+                }
+                .await?;
+            }
+            RbacChange::DeleteUser(name) => {
+                auth.user_delete(name.as_str()).await?;
+            }
+            RbacChange::GrantRole { user, role } => {
+                auth.user_grant_role(user.as_str(), role.as_str()).await?;
+            }
+            RbacChange::RevokeRole { user, role } => {
+                auth.user_revoke_role(user.as_str(), role.as_str()).await?;
+            }
+            RbacChange::DeleteRole(role) => {
+                auth.role_delete(role.as_str()).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::get_client;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_rbac_config_json() {
+        let config: RbacConfig = serde_json::from_str(
+            r#"{
+                "roles": [{
+                    "name": "app",
+                    "permissions": [
+                        { "type": "readwrite", "key": "/app/", "prefix": true },
+                        { "type": "read", "key": "/config" }
+                    ]
+                }],
+                "users": [{ "name": "app", "password": "secret", "roles": ["app"] }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.roles().len(), 1);
+        let permissions = config.roles()[0].permissions();
+        assert_eq!(
+            permissions[0].to_permission(),
+            Permission::read_write("/app/").with_prefix()
+        );
+        assert_eq!(permissions[1].to_permission(), Permission::read("/config"));
+        assert_eq!(config.users()[0].roles(), ["app"]);
+        assert!(!format!("{:?}", config).contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn test_rbac_reconcile() -> Result<()> {
+        let client = get_client().await?;
+        let _ = client.user_delete("rbac-user").await;
+        let _ = client.role_delete("rbac-role").await;
+
+        let config =
+            RbacConfig::new()
+                .with_role(RoleSpec::new("rbac-role").with_permission(
+                    PermissionSpec::new(PermissionType::Read, "rbac/").with_prefix(),
+                ))
+                .with_user(
+                    UserSpec::new("rbac-user")
+                        .with_password("pwd")
+                        .with_role("rbac-role"),
+                );
+
+        let plan = client.plan_rbac(&config).await?;
+        assert_eq!(plan.changes().len(), 4);
+        client.apply_rbac(&plan).await?;
+
+        // converged
+        assert!(client.plan_rbac(&config).await?.is_empty());
+
+        // drift: the permission changes, the role is revoked from the user
+        let config =
+            RbacConfig::new()
+                .with_role(RoleSpec::new("rbac-role").with_permission(
+                    PermissionSpec::new(PermissionType::Write, "rbac/").with_prefix(),
+                ))
+                .with_user(UserSpec::new("rbac-user"));
+        let plan = client.plan_rbac(&config).await?;
+        assert_eq!(plan.changes().len(), 3);
+        client.apply_rbac(&plan).await?;
+        assert!(client.plan_rbac(&config).await?.is_empty());
+
+        client.user_delete("rbac-user").await?;
+        client.role_delete("rbac-role").await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+//! Distributed barriers.
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::recipes::{put_unique_key, wait_event};
+use crate::rpc::kv::{Compare, CompareOp, GetOptions, KvClient, Txn, TxnOp};
+use crate::rpc::watch::{EventType, WatchClient};
+
+/// A barrier blocks processes in [`Barrier::wait`] until the barrier key is deleted.
+/// Any process may hold the barrier by creating the key, and release it by deleting the key.
+#[derive(Clone)]
+pub struct Barrier {
+    kv: KvClient,
+    watch: WatchClient,
+    key: Vec<u8>,
+}
+
+impl Barrier {
+    /// Creates a barrier on the given `key`.
+    #[inline]
+    pub fn new(client: &Client, key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            kv: client.kv_client(),
+            watch: client.watch_client(),
+            key: key.into(),
+        }
+    }
+
+    /// Holds the barrier by creating the barrier key.
+    /// Returns an error if the barrier is already held.
+    pub async fn hold(&self) -> Result<()> {
+        let txn = Txn::new()
+            .when(vec![Compare::create_revision(
+                self.key.clone(),
+                CompareOp::Equal,
+                0,
+            )])
+            .and_then(vec![TxnOp::put(self.key.clone(), "", None)]);
+        if self.kv.txn(txn).await?.succeeded() {
+            Ok(())
+        } else {
+            Err(Error::BarrierError("barrier is already held".to_string()))
+        }
+    }
+
+    /// Releases the barrier by deleting the barrier key, unblocking all waiters.
+    #[inline]
+    pub async fn release(&self) -> Result<()> {
+        self.kv.delete(self.key.clone(), None).await?;
+        Ok(())
+    }
+
+    /// Waits until the barrier is released.
+    /// Returns immediately if the barrier is not held.
+    pub async fn wait(&self) -> Result<()> {
+        let resp = self
+            .kv
+            .get(self.key.clone(), Some(GetOptions::new().with_count_only()))
+            .await?;
+        if resp.count() == 0 {
+            return Ok(());
+        }
+
+        let revision = resp.header().map(|h| h.revision()).unwrap_or_default();
+        wait_event(
+            &self.watch,
+            self.key.clone(),
+            revision + 1,
+            EventType::Delete,
+        )
+        .await
+    }
+}
+
+/// A double barrier blocks processes in [`DoubleBarrier::enter`] until the expected
+/// number of processes have entered, and in [`DoubleBarrier::leave`] until all of them
+/// have left.
+///
+/// Each participant registers an ephemeral key attached to a lease, so that a failed
+/// process is removed from the barrier once its lease expires. The lease must be kept
+/// alive by the caller while the process is in the barrier.
+pub struct DoubleBarrier {
+    kv: KvClient,
+    watch: WatchClient,
+    key: Vec<u8>,
+    count: usize,
+    lease: i64,
+    my_key: Option<Vec<u8>>,
+}
+
+impl DoubleBarrier {
+    /// Creates a double barrier on the given `key` for `count` participants,
+    /// registering this participant with `lease`.
+    #[inline]
+    pub fn new(client: &Client, key: impl Into<Vec<u8>>, count: usize, lease: i64) -> Self {
+        Self {
+            kv: client.kv_client(),
+            watch: client.watch_client(),
+            key: key.into(),
+            count,
+            lease,
+            my_key: None,
+        }
+    }
+
+    /// Key with a suffix appended to the barrier key.
+    #[inline]
+    fn sub_key(&self, suffix: &str) -> Vec<u8> {
+        let mut key = self.key.clone();
+        key.extend_from_slice(suffix.as_bytes());
+        key
+    }
+
+    /// Number of participants that have entered the barrier.
+    async fn entered(&self) -> Result<usize> {
+        let resp = self
+            .kv
+            .get(
+                self.sub_key("/waiters"),
+                Some(GetOptions::new().with_prefix().with_count_only()),
+            )
+            .await?;
+        Ok(resp.count() as usize)
+    }
+
+    /// Waits for `count` participants to enter the barrier.
+    pub async fn enter(&mut self) -> Result<()> {
+        if self.entered().await? >= self.count {
+            return Err(Error::BarrierError("too many clients".to_string()));
+        }
+
+        let (my_key, revision) =
+            put_unique_key(&self.kv, &self.sub_key("/waiters"), self.lease).await?;
+        self.my_key = Some(my_key);
+
+        let entered = self.entered().await?;
+        if entered > self.count {
+            return Err(Error::BarrierError("too many clients".to_string()));
+        }
+
+        if entered == self.count {
+            // unblock the waiters
+            self.kv.put(self.sub_key("/ready"), "", None).await?;
+            return Ok(());
+        }
+
+        wait_event(
+            &self.watch,
+            self.sub_key("/ready"),
+            revision,
+            EventType::Put,
+        )
+        .await
+    }
+
+    /// Waits for all participants to leave the barrier.
+    pub async fn leave(&mut self) -> Result<()> {
+        let my_key = match self.my_key.clone() {
+            Some(key) => key,
+            None => return Err(Error::BarrierError("barrier is not entered".to_string())),
+        };
+
+        loop {
+            let resp = self
+                .kv
+                .get(
+                    self.sub_key("/waiters"),
+                    Some(GetOptions::new().with_prefix()),
+                )
+                .await?;
+            let kvs = resp.kvs();
+            let (lowest, highest) = match (
+                kvs.iter().min_by_key(|kv| kv.mod_revision()),
+                kvs.iter().max_by_key(|kv| kv.mod_revision()),
+            ) {
+                (Some(lowest), Some(highest)) => (lowest, highest),
+                _ => break,
+            };
+            let is_lowest = lowest.key() == my_key.as_slice();
+
+            if kvs.len() == 1 && is_lowest {
+                // this is the only participant in the barrier; finish up
+                self.kv.delete(self.sub_key("/ready"), None).await?;
+                self.kv.delete(my_key, None).await?;
+                break;
+            }
+
+            // the lowest participant waits on the highest one; others delete
+            // themselves and wait on the lowest one. If a process fails, its
+            // ephemeral key is removed when its lease expires and the barrier resumes.
+            if is_lowest {
+                wait_event(
+                    &self.watch,
+                    highest.key(),
+                    highest.mod_revision(),
+                    EventType::Delete,
+                )
+                .await?;
+            } else {
+                self.kv.delete(my_key.clone(), None).await?;
+                wait_event(
+                    &self.watch,
+                    lowest.key(),
+                    highest.mod_revision(),
+                    EventType::Delete,
+                )
+                .await?;
+            }
+        }
+
+        self.my_key = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::get_client;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_barrier() -> Result<()> {
+        let client = get_client().await?;
+        let barrier = Barrier::new(&client, "barrier-test");
+        let _ = barrier.release().await;
+
+        // not held, returns immediately
+        barrier.wait().await?;
+
+        barrier.hold().await?;
+        barrier.hold().await.unwrap_err();
+
+        let waiter = {
+            let barrier = barrier.clone();
+            tokio::spawn(async move { barrier.wait().await })
+        };
+        assert!(timeout(Duration::from_millis(200), barrier.wait())
+            .await
+            .is_err());
+
+        barrier.release().await?;
+        waiter.await.unwrap()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_double_barrier() -> Result<()> {
+        let client = get_client().await?;
+        let _ = client
+            .delete(
+                "double-barrier-test",
+                Some(crate::DeleteOptions::new().with_prefix()),
+            )
+            .await;
+
+        let mut handles = Vec::new();
+        for _ in 0..3 {
+            let client = client.clone();
+            handles.push(tokio::spawn(async move {
+                let lease = client.lease_grant(60, None).await?.id();
+                let mut barrier = DoubleBarrier::new(&client, "double-barrier-test", 3, lease);
+                barrier.enter().await?;
+                barrier.leave().await?;
+                client.lease_revoke(lease).await?;
+                Ok::<(), Error>(())
+            }));
+        }
+
+        for handle in handles {
+            timeout(Duration::from_secs(10), handle)
+                .await
+                .expect("double barrier timed out")
+                .unwrap()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+//! Higher-level synchronization recipes built on top of the etcd v3 API.
+
+pub mod barrier;
+
+use crate::error::{Error, Result};
+use crate::rpc::kv::{Compare, CompareOp, KvClient, PutOptions, Txn, TxnOp};
+use crate::rpc::watch::{EventType, WatchClient, WatchOptions};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Waits on `key` from `revision` until an event of `event_type` happens.
+async fn wait_event(
+    watch: &WatchClient,
+    key: impl Into<Vec<u8>>,
+    revision: i64,
+    event_type: EventType,
+) -> Result<()> {
+    let options = WatchOptions::new().with_start_revision(revision);
+    let (mut watcher, mut stream) = watch.watch(key, Some(options)).await?;
+
+    while let Some(resp) = stream.message().await? {
+        if resp.canceled() {
+            return Err(Error::WatchError(format!(
+                "watcher canceled: {}",
+                resp.cancel_reason()
+            )));
+        }
+
+        if resp
+            .events()
+            .iter()
+            .any(|event| event.event_type() == event_type)
+        {
+            watcher.cancel().await?;
+            return Ok(());
+        }
+    }
+
+    Err(Error::WatchError("watch stream closed".to_string()))
+}
+
+/// Puts a new key `prefix/<unique suffix>` attached to `lease`.
+/// Returns the created key and the revision of its creation.
+async fn put_unique_key(kv: &KvClient, prefix: &[u8], lease: i64) -> Result<(Vec<u8>, i64)> {
+    loop {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let mut key = prefix.to_vec();
+        key.extend_from_slice(format!("/{:x}", nanos).as_bytes());
+
+        let txn = Txn::new()
+            .when(vec![Compare::create_revision(
+                key.clone(),
+                CompareOp::Equal,
+                0,
+            )])
+            .and_then(vec![TxnOp::put(
+                key.clone(),
+                "",
+                Some(PutOptions::new().with_lease(lease)),
+            )]);
+        let resp = kv.txn(txn).await?;
+        if resp.succeeded() {
+            let revision = resp.header().map(|h| h.revision()).unwrap_or_default();
+            return Ok((key, revision));
+        }
+    }
+}
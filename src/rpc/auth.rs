@@ -32,32 +32,56 @@ use crate::rpc::pb::etcdserverpb::{
     AuthenticateRequest as PbAuthenticateRequest, AuthenticateResponse as PbAuthenticateResponse,
 };
 use crate::rpc::ResponseHeader;
-use crate::rpc::{get_prefix, KeyRange};
-use http::HeaderValue;
+use crate::rpc::{get_prefix, KeyRange, Metadata};
+use http::{HeaderMap, HeaderValue};
 use std::{string::String, sync::Arc};
 use tonic::transport::Channel;
 use tonic::{IntoRequest, Request};
 
 /// Client for Auth operations.
-#[repr(transparent)]
+#[cfg_attr(
+    not(any(feature = "compression", feature = "gateway")),
+    repr(transparent)
+)]
 #[derive(Clone)]
 pub struct AuthClient {
     inner: PbAuthClient<AuthService<Channel>>,
+    /// `inner` without compression
+    #[cfg(feature = "compression")]
+    uncompressed: PbAuthClient<AuthService<Channel>>,
+    /// sends the requests through the grpc-gateway instead
+    #[cfg(feature = "gateway")]
+    gateway: Option<crate::GatewayClient>,
 }
 
+impl_with_compression!(AuthClient);
+impl_with_gateway!(AuthClient);
+
 impl AuthClient {
     /// Creates an auth client.
     #[inline]
-    pub(crate) fn new(channel: Channel, auth_token: Option<Arc<HeaderValue>>) -> Self {
-        let inner = PbAuthClient::new(AuthService::new(channel, auth_token));
-        Self { inner }
+    pub(crate) fn new(
+        channel: Channel,
+        auth_token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+    ) -> Self {
+        let inner = PbAuthClient::new(AuthService::new(channel, auth_token, metadata));
+        Self {
+            #[cfg(feature = "compression")]
+            uncompressed: inner.clone(),
+            inner,
+            #[cfg(feature = "gateway")]
+            gateway: None,
+        }
     }
 
     /// Enables authentication.
     #[inline]
-    pub async fn auth_enable(&mut self) -> Result<AuthEnableResponse> {
+    pub async fn auth_enable(&self) -> Result<AuthEnableResponse> {
+        via_gateway!(self, |gateway| gateway.auth_enable());
         let resp = self
             .inner
+            .clone()
             .auth_enable(AuthEnableOptions::new())
             .await?
             .into_inner();
@@ -66,9 +90,11 @@ impl AuthClient {
 
     /// Disables authentication.
     #[inline]
-    pub async fn auth_disable(&mut self) -> Result<AuthDisableResponse> {
+    pub async fn auth_disable(&self) -> Result<AuthDisableResponse> {
+        via_gateway!(self, |gateway| gateway.auth_disable());
         let resp = self
             .inner
+            .clone()
             .auth_disable(AuthDisableOptions::new())
             .await?
             .into_inner();
@@ -78,12 +104,14 @@ impl AuthClient {
     /// Processes an authenticate request.
     #[inline]
     pub async fn authenticate(
-        &mut self,
+        &self,
         name: String,
         password: String,
     ) -> Result<AuthenticateResponse> {
+        via_gateway!(self, |gateway| gateway.authenticate(name, password));
         let resp = self
             .inner
+            .clone()
             .authenticate(AuthenticateOptions::new().with_user(name, password))
             .await?
             .into_inner();
@@ -92,9 +120,11 @@ impl AuthClient {
 
     /// Adds role
     #[inline]
-    pub async fn role_add(&mut self, name: impl Into<String>) -> Result<RoleAddResponse> {
+    pub async fn role_add(&self, name: impl Into<String>) -> Result<RoleAddResponse> {
+        via_gateway!(self, |gateway| gateway.role_add(name));
         let resp = self
             .inner
+            .clone()
             .role_add(RoleAddOptions::new(name.into()))
             .await?
             .into_inner();
@@ -103,9 +133,11 @@ impl AuthClient {
 
     /// Deletes role
     #[inline]
-    pub async fn role_delete(&mut self, name: impl Into<String>) -> Result<RoleDeleteResponse> {
+    pub async fn role_delete(&self, name: impl Into<String>) -> Result<RoleDeleteResponse> {
+        via_gateway!(self, |gateway| gateway.role_delete(name));
         let resp = self
             .inner
+            .clone()
             .role_delete(RoleDeleteOptions::new(name.into()))
             .await?
             .into_inner();
@@ -114,9 +146,11 @@ impl AuthClient {
 
     /// Gets role
     #[inline]
-    pub async fn role_get(&mut self, name: impl Into<String>) -> Result<RoleGetResponse> {
+    pub async fn role_get(&self, name: impl Into<String>) -> Result<RoleGetResponse> {
+        via_gateway!(self, |gateway| gateway.role_get(name));
         let resp = self
             .inner
+            .clone()
             .role_get(RoleGetOptions::new(name.into()))
             .await?
             .into_inner();
@@ -125,9 +159,11 @@ impl AuthClient {
 
     /// Lists role
     #[inline]
-    pub async fn role_list(&mut self) -> Result<RoleListResponse> {
+    pub async fn role_list(&self) -> Result<RoleListResponse> {
+        via_gateway!(self, |gateway| gateway.role_list());
         let resp = self
             .inner
+            .clone()
             .role_list(AuthRoleListOptions {})
             .await?
             .into_inner();
@@ -137,12 +173,14 @@ impl AuthClient {
     /// Grants role permission
     #[inline]
     pub async fn role_grant_permission(
-        &mut self,
+        &self,
         name: impl Into<String>,
         perm: Permission,
     ) -> Result<RoleGrantPermissionResponse> {
+        via_gateway!(self, |gateway| gateway.role_grant_permission(name, perm));
         let resp = self
             .inner
+            .clone()
             .role_grant_permission(RoleGrantPermissionOptions::new(name.into(), perm))
             .await?
             .into_inner();
@@ -152,18 +190,22 @@ impl AuthClient {
     /// Revokes role permission
     #[inline]
     pub async fn role_revoke_permission(
-        &mut self,
+        &self,
         name: impl Into<String>,
         key: impl Into<Vec<u8>>,
         options: Option<RoleRevokePermissionOptions>,
     ) -> Result<RoleRevokePermissionResponse> {
+        via_gateway!(self, |gateway| gateway
+            .role_revoke_permission(name, key, options));
         let resp = self
             .inner
+            .clone()
             .role_revoke_permission(
                 options
                     .unwrap_or_default()
                     .with_name(name.into())
-                    .with_key(key.into()),
+                    .with_key(key.into())
+                    .request()?,
             )
             .await?
             .into_inner();
@@ -173,18 +215,21 @@ impl AuthClient {
     /// Adds user
     #[inline]
     pub async fn user_add(
-        &mut self,
+        &self,
         name: impl Into<String>,
         password: impl Into<String>,
         options: Option<UserAddOptions>,
     ) -> Result<UserAddResponse> {
+        via_gateway!(self, |gateway| gateway.user_add(name, password, options));
         let resp = self
             .inner
+            .clone()
             .user_add(
                 options
                     .unwrap_or_default()
                     .with_name(name.into())
-                    .with_pwd(password.into()),
+                    .with_pwd(password.into())
+                    .request()?,
             )
             .await?
             .into_inner();
@@ -193,9 +238,11 @@ impl AuthClient {
 
     /// Gets user
     #[inline]
-    pub async fn user_get(&mut self, name: impl Into<String>) -> Result<UserGetResponse> {
+    pub async fn user_get(&self, name: impl Into<String>) -> Result<UserGetResponse> {
+        via_gateway!(self, |gateway| gateway.user_get(name));
         let resp = self
             .inner
+            .clone()
             .user_get(UserGetOptions::new(name.into()))
             .await?
             .into_inner();
@@ -204,9 +251,11 @@ impl AuthClient {
 
     /// Lists user
     #[inline]
-    pub async fn user_list(&mut self) -> Result<UserListResponse> {
+    pub async fn user_list(&self) -> Result<UserListResponse> {
+        via_gateway!(self, |gateway| gateway.user_list());
         let resp = self
             .inner
+            .clone()
             .user_list(AuthUserListOptions {})
             .await?
             .into_inner();
@@ -215,9 +264,11 @@ impl AuthClient {
 
     /// Deletes user
     #[inline]
-    pub async fn user_delete(&mut self, name: impl Into<String>) -> Result<UserDeleteResponse> {
+    pub async fn user_delete(&self, name: impl Into<String>) -> Result<UserDeleteResponse> {
+        via_gateway!(self, |gateway| gateway.user_delete(name));
         let resp = self
             .inner
+            .clone()
             .user_delete(UserDeleteOptions::new(name.into()))
             .await?
             .into_inner();
@@ -227,12 +278,14 @@ impl AuthClient {
     /// Change user's password
     #[inline]
     pub async fn user_change_password(
-        &mut self,
+        &self,
         name: impl Into<String>,
         password: impl Into<String>,
     ) -> Result<UserChangePasswordResponse> {
+        via_gateway!(self, |gateway| gateway.user_change_password(name, password));
         let resp = self
             .inner
+            .clone()
             .user_change_password(UserChangePasswordOptions::new(name.into(), password.into()))
             .await?
             .into_inner();
@@ -242,12 +295,14 @@ impl AuthClient {
     /// Grant role for an user
     #[inline]
     pub async fn user_grant_role(
-        &mut self,
+        &self,
         name: impl Into<String>,
         role: impl Into<String>,
     ) -> Result<UserGrantRoleResponse> {
+        via_gateway!(self, |gateway| gateway.user_grant_role(name, role));
         let resp = self
             .inner
+            .clone()
             .user_grant_role(UserGrantRoleOptions::new(name.into(), role.into()))
             .await?
             .into_inner();
@@ -257,12 +312,14 @@ impl AuthClient {
     /// Revoke role for an user
     #[inline]
     pub async fn user_revoke_role(
-        &mut self,
+        &self,
         name: impl Into<String>,
         role: impl Into<String>,
     ) -> Result<UserRevokeRoleResponse> {
+        via_gateway!(self, |gateway| gateway.user_revoke_role(name, role));
         let resp = self
             .inner
+            .clone()
             .user_revoke_role(UserRevokeRoleOptions::new(name.into(), role.into()))
             .await?
             .into_inner();
@@ -305,7 +362,7 @@ pub struct AuthEnableResponse(PbAuthEnableResponse);
 impl AuthEnableResponse {
     /// Creates a new `AuthEnableResponse` from pb auth response.
     #[inline]
-    const fn new(resp: PbAuthEnableResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthEnableResponse) -> Self {
         Self(resp)
     }
 
@@ -357,7 +414,7 @@ pub struct AuthDisableResponse(PbAuthDisableResponse);
 impl AuthDisableResponse {
     /// Creates a new `AuthDisableResponse` from pb auth response.
     #[inline]
-    const fn new(resp: PbAuthDisableResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthDisableResponse) -> Self {
         Self(resp)
     }
 
@@ -421,7 +478,7 @@ pub struct AuthenticateResponse(PbAuthenticateResponse);
 impl AuthenticateResponse {
     /// Creates a new `AuthenticateResponse` from pb auth response.
     #[inline]
-    const fn new(resp: PbAuthenticateResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthenticateResponse) -> Self {
         Self(resp)
     }
 
@@ -482,7 +539,7 @@ pub struct RoleAddResponse(PbAuthRoleAddResponse);
 impl RoleAddResponse {
     /// Creates a new `RoleAddResponse` from pb role add response.
     #[inline]
-    const fn new(resp: PbAuthRoleAddResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthRoleAddResponse) -> Self {
         Self(resp)
     }
 
@@ -535,7 +592,7 @@ pub struct RoleDeleteResponse(PbAuthRoleDeleteResponse);
 impl RoleDeleteResponse {
     /// Creates a new `RoleDeleteResponse` from pb role delete response.
     #[inline]
-    const fn new(resp: PbAuthRoleDeleteResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthRoleDeleteResponse) -> Self {
         Self(resp)
     }
 
@@ -727,6 +784,13 @@ impl Permission {
     pub const fn is_prefix(&self) -> bool {
         self.with_prefix
     }
+
+    /// The operation type, key and range end as sent to etcd.
+    #[inline]
+    pub(crate) fn normalized(&self) -> (i32, Vec<u8>, Vec<u8>) {
+        let perm = PbPermission::from(self.clone());
+        (perm.perm_type, perm.key, perm.range_end)
+    }
 }
 
 impl PartialEq for Permission {
@@ -800,7 +864,7 @@ pub struct RoleGetResponse(PbAuthRoleGetResponse);
 impl RoleGetResponse {
     /// Creates a new `RoleGetResponse` from pb role get response.
     #[inline]
-    const fn new(resp: PbAuthRoleGetResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthRoleGetResponse) -> Self {
         Self(resp)
     }
 
@@ -839,7 +903,7 @@ pub struct RoleListResponse(PbAuthRoleListResponse);
 impl RoleListResponse {
     /// Creates a new `RoleListResponse` from pb role list response.
     #[inline]
-    const fn new(resp: PbAuthRoleListResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthRoleListResponse) -> Self {
         Self(resp)
     }
 
@@ -901,7 +965,7 @@ pub struct RoleGrantPermissionResponse(PbAuthRoleGrantPermissionResponse);
 impl RoleGrantPermissionResponse {
     /// Creates a new `RoleGrantPermissionResponse` from pb role grant permission response.
     #[inline]
-    const fn new(resp: PbAuthRoleGrantPermissionResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthRoleGrantPermissionResponse) -> Self {
         Self(resp)
     }
 
@@ -923,6 +987,7 @@ impl RoleGrantPermissionResponse {
 pub struct RoleRevokePermissionOptions {
     req: PbAuthRoleRevokePermissionRequest,
     key_range: KeyRange,
+    metadata: Metadata,
 }
 
 impl RoleRevokePermissionOptions {
@@ -936,19 +1001,20 @@ impl RoleRevokePermissionOptions {
                 range_end: Vec::new(),
             },
             key_range: KeyRange::new(),
+            metadata: Metadata::new(),
         }
     }
 
     /// Sets name.
     #[inline]
-    fn with_name(mut self, name: String) -> Self {
+    pub(crate) fn with_name(mut self, name: String) -> Self {
         self.req.role = name;
         self
     }
 
     /// Sets key.
     #[inline]
-    fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+    pub(crate) fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
         self.key_range.with_key(key);
         self
     }
@@ -981,6 +1047,20 @@ impl RoleRevokePermissionOptions {
         self.key_range.with_all_keys();
         self
     }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbAuthRoleRevokePermissionRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<RoleRevokePermissionOptions> for PbAuthRoleRevokePermissionRequest {
@@ -1009,7 +1089,7 @@ pub struct RoleRevokePermissionResponse(PbAuthRoleRevokePermissionResponse);
 impl RoleRevokePermissionResponse {
     /// Creates a new `RoleRevokePermissionResponse` from pb role revoke permission response.
     #[inline]
-    const fn new(resp: PbAuthRoleRevokePermissionResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthRoleRevokePermissionResponse) -> Self {
         Self(resp)
     }
 
@@ -1028,46 +1108,65 @@ impl RoleRevokePermissionResponse {
 
 /// Options for `UserAdd` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct UserAddOptions(PbAuthUserAddRequest);
+pub struct UserAddOptions {
+    req: PbAuthUserAddRequest,
+    metadata: Metadata,
+}
 
 impl UserAddOptions {
     /// Creates a `UserAddOptions`.
     #[inline]
     pub const fn new() -> Self {
-        Self(PbAuthUserAddRequest {
-            name: String::new(),
-            password: String::new(),
-            options: Some(PbUserAddOptions { no_password: false }),
-        })
+        Self {
+            req: PbAuthUserAddRequest {
+                name: String::new(),
+                password: String::new(),
+                options: Some(PbUserAddOptions { no_password: false }),
+            },
+            metadata: Metadata::new(),
+        }
     }
 
     /// Set name.
     #[inline]
-    fn with_name(mut self, name: impl Into<String>) -> Self {
-        self.0.name = name.into();
+    pub(crate) fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.req.name = name.into();
         self
     }
 
     /// Set password.
     #[inline]
-    fn with_pwd(mut self, password: impl Into<String>) -> Self {
-        self.0.password = password.into();
+    pub(crate) fn with_pwd(mut self, password: impl Into<String>) -> Self {
+        self.req.password = password.into();
         self
     }
 
     /// Set no password.
     #[inline]
     pub const fn with_no_pwd(mut self) -> Self {
-        self.0.options = Some(PbUserAddOptions { no_password: true });
+        self.req.options = Some(PbUserAddOptions { no_password: true });
         self
     }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbAuthUserAddRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<UserAddOptions> for PbAuthUserAddRequest {
     #[inline]
     fn from(options: UserAddOptions) -> Self {
-        options.0
+        options.req
     }
 }
 
@@ -1087,7 +1186,7 @@ pub struct UserAddResponse(PbAuthUserAddResponse);
 impl UserAddResponse {
     /// Creates a new `UserAddReqResponse` from pb user add response.
     #[inline]
-    const fn new(resp: PbAuthUserAddResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthUserAddResponse) -> Self {
         Self(resp)
     }
 
@@ -1140,7 +1239,7 @@ pub struct UserGetResponse(PbAuthUserGetResponse);
 impl UserGetResponse {
     /// Creates a new `UserGetResponse` from pb user get response.
     #[inline]
-    const fn new(resp: PbAuthUserGetResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthUserGetResponse) -> Self {
         Self(resp)
     }
 
@@ -1175,7 +1274,7 @@ pub struct UserListResponse(PbAuthUserListResponse);
 impl UserListResponse {
     /// Creates a new `UserListResponse` from pb user list response.
     #[inline]
-    const fn new(resp: PbAuthUserListResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthUserListResponse) -> Self {
         Self(resp)
     }
 
@@ -1234,7 +1333,7 @@ pub struct UserDeleteResponse(PbAuthUserDeleteResponse);
 impl UserDeleteResponse {
     /// Creates a new `UserDeleteResponse` from pb user delete response.
     #[inline]
-    const fn new(resp: PbAuthUserDeleteResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthUserDeleteResponse) -> Self {
         Self(resp)
     }
 
@@ -1290,7 +1389,7 @@ pub struct UserChangePasswordResponse(PbAuthUserChangePasswordResponse);
 impl UserChangePasswordResponse {
     /// Creates a new `UserChangePasswordResponse` from pb user change password response.
     #[inline]
-    const fn new(resp: PbAuthUserChangePasswordResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthUserChangePasswordResponse) -> Self {
         Self(resp)
     }
 
@@ -1343,7 +1442,7 @@ pub struct UserGrantRoleResponse(PbAuthUserGrantRoleResponse);
 impl UserGrantRoleResponse {
     /// Creates a new `UserGrantRoleResponse` from pb user grant role response.
     #[inline]
-    const fn new(resp: PbAuthUserGrantRoleResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthUserGrantRoleResponse) -> Self {
         Self(resp)
     }
 
@@ -1396,7 +1495,7 @@ pub struct UserRevokeRoleResponse(PbAuthUserRevokeRoleResponse);
 impl UserRevokeRoleResponse {
     /// Creates a new `UserRevokeRoleResponse` from pb user revoke role response.
     #[inline]
-    const fn new(resp: PbAuthUserRevokeRoleResponse) -> Self {
+    pub(crate) const fn new(resp: PbAuthUserRevokeRoleResponse) -> Self {
         Self(resp)
     }
 
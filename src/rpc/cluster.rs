@@ -11,37 +11,51 @@ use crate::rpc::pb::etcdserverpb::{
     MemberRemoveResponse as PbMemberRemoveResponse, MemberUpdateRequest as PbMemberUpdateRequest,
     MemberUpdateResponse as PbMemberUpdateResponse,
 };
-use crate::rpc::ResponseHeader;
-use http::HeaderValue;
+use crate::rpc::{Metadata, ResponseHeader};
+use http::{HeaderMap, HeaderValue};
 use std::{string::String, sync::Arc};
 use tonic::transport::Channel;
 use tonic::{IntoRequest, Request};
 
 /// Client for Cluster operations.
-#[repr(transparent)]
+#[cfg_attr(not(feature = "compression"), repr(transparent))]
 #[derive(Clone)]
 pub struct ClusterClient {
     inner: PbClusterClient<AuthService<Channel>>,
+    /// `inner` without compression
+    #[cfg(feature = "compression")]
+    uncompressed: PbClusterClient<AuthService<Channel>>,
 }
 
+impl_with_compression!(ClusterClient);
+
 impl ClusterClient {
     /// Creates an Cluster client.
     #[inline]
-    pub(crate) fn new(channel: Channel, auth_token: Option<Arc<HeaderValue>>) -> Self {
-        let inner = PbClusterClient::new(AuthService::new(channel, auth_token));
-        Self { inner }
+    pub(crate) fn new(
+        channel: Channel,
+        auth_token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+    ) -> Self {
+        let inner = PbClusterClient::new(AuthService::new(channel, auth_token, metadata));
+        Self {
+            #[cfg(feature = "compression")]
+            uncompressed: inner.clone(),
+            inner,
+        }
     }
 
     /// Adds a new member into the cluster.
     #[inline]
     pub async fn member_add(
-        &mut self,
+        &self,
         urls: impl Into<Vec<String>>,
         options: Option<MemberAddOptions>,
     ) -> Result<MemberAddResponse> {
         let resp = self
             .inner
-            .member_add(options.unwrap_or_default().with_urls(urls))
+            .clone()
+            .member_add(options.unwrap_or_default().with_urls(urls).request()?)
             .await?
             .into_inner();
 
@@ -50,9 +64,10 @@ impl ClusterClient {
 
     /// Removes an existing member from the cluster.
     #[inline]
-    pub async fn member_remove(&mut self, id: u64) -> Result<MemberRemoveResponse> {
+    pub async fn member_remove(&self, id: u64) -> Result<MemberRemoveResponse> {
         let resp = self
             .inner
+            .clone()
             .member_remove(MemberRemoveOptions::new().with_id(id))
             .await?
             .into_inner();
@@ -62,12 +77,13 @@ impl ClusterClient {
     /// Updates the member configuration.
     #[inline]
     pub async fn member_update(
-        &mut self,
+        &self,
         id: u64,
         url: impl Into<Vec<String>>,
     ) -> Result<MemberUpdateResponse> {
         let resp = self
             .inner
+            .clone()
             .member_update(MemberUpdateOptions::new().with_option(id, url))
             .await?
             .into_inner();
@@ -76,9 +92,10 @@ impl ClusterClient {
 
     /// Lists all the members in the cluster.
     #[inline]
-    pub async fn member_list(&mut self) -> Result<MemberListResponse> {
+    pub async fn member_list(&self) -> Result<MemberListResponse> {
         let resp = self
             .inner
+            .clone()
             .member_list(PbMemberListRequest {})
             .await?
             .into_inner();
@@ -87,9 +104,10 @@ impl ClusterClient {
 
     /// Promotes a member from raft learner (non-voting) to raft voting member.
     #[inline]
-    pub async fn member_promote(&mut self, id: u64) -> Result<MemberPromoteResponse> {
+    pub async fn member_promote(&self, id: u64) -> Result<MemberPromoteResponse> {
         let resp = self
             .inner
+            .clone()
             .member_promote(MemberPromoteOptions::new().with_id(id))
             .await?
             .into_inner();
@@ -99,37 +117,56 @@ impl ClusterClient {
 
 /// Options for `MemberAdd` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct MemberAddOptions(PbMemberAddRequest);
+pub struct MemberAddOptions {
+    req: PbMemberAddRequest,
+    metadata: Metadata,
+}
 
 impl MemberAddOptions {
     #[inline]
     fn with_urls(mut self, urls: impl Into<Vec<String>>) -> Self {
-        self.0.peer_ur_ls = urls.into();
+        self.req.peer_ur_ls = urls.into();
         self
     }
 
     /// Creates a `MemberAddOptions`.
     #[inline]
     pub const fn new() -> Self {
-        Self(PbMemberAddRequest {
-            peer_ur_ls: Vec::new(),
-            is_learner: false,
-        })
+        Self {
+            req: PbMemberAddRequest {
+                peer_ur_ls: Vec::new(),
+                is_learner: false,
+            },
+            metadata: Metadata::new(),
+        }
     }
 
     /// Sets the member as a learner.
     #[inline]
     pub const fn with_is_learner(mut self) -> Self {
-        self.0.is_learner = true;
+        self.req.is_learner = true;
+        self
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
         self
     }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbMemberAddRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<MemberAddOptions> for PbMemberAddRequest {
     #[inline]
     fn from(options: MemberAddOptions) -> Self {
-        options.0
+        options.req
     }
 }
 
@@ -9,8 +9,8 @@ use crate::rpc::pb::v3electionpb::{
     ProclaimRequest as PbProclaimRequest, ProclaimResponse as PbProclaimResponse,
     ResignRequest as PbResignRequest, ResignResponse as PbResignResponse,
 };
-use crate::rpc::{KeyValue, ResponseHeader};
-use http::HeaderValue;
+use crate::rpc::{KeyValue, Metadata, ResponseHeader};
+use http::{HeaderMap, HeaderValue};
 use std::task::{Context, Poll};
 use std::{pin::Pin, sync::Arc};
 use tokio_stream::Stream;
@@ -18,10 +18,13 @@ use tonic::transport::Channel;
 use tonic::{IntoRequest, Request, Streaming};
 
 /// Client for Elect operations.
-#[repr(transparent)]
+#[cfg_attr(not(feature = "compression"), repr(transparent))]
 #[derive(Clone)]
 pub struct ElectionClient {
     inner: PbElectionClient<AuthService<Channel>>,
+    /// `inner` without compression
+    #[cfg(feature = "compression")]
+    uncompressed: PbElectionClient<AuthService<Channel>>,
 }
 
 /// Options for `campaign` operation.
@@ -77,37 +80,56 @@ impl IntoRequest<PbCampaignRequest> for CampaignOptions {
 
 /// Options for `proclaim` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct ProclaimOptions(PbProclaimRequest);
+pub struct ProclaimOptions {
+    req: PbProclaimRequest,
+    metadata: Metadata,
+}
 
 impl ProclaimOptions {
     #[inline]
     pub const fn new() -> Self {
-        Self(PbProclaimRequest {
-            leader: None,
-            value: Vec::new(),
-        })
+        Self {
+            req: PbProclaimRequest {
+                leader: None,
+                value: Vec::new(),
+            },
+            metadata: Metadata::new(),
+        }
     }
 
     /// The initial proclaimed value set when the campaigner wins the election.
     #[inline]
     fn with_value(mut self, value: impl Into<Vec<u8>>) -> Self {
-        self.0.value = value.into();
+        self.req.value = value.into();
         self
     }
 
     /// The leadership hold on the election.
     #[inline]
     pub fn with_leader(mut self, leader: LeaderKey) -> Self {
-        self.0.leader = Some(leader.into());
+        self.req.leader = Some(leader.into());
         self
     }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbProclaimRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<ProclaimOptions> for PbProclaimRequest {
     #[inline]
     fn from(options: ProclaimOptions) -> Self {
-        options.0
+        options.req
     }
 }
 
@@ -153,27 +175,46 @@ impl IntoRequest<PbLeaderRequest> for LeaderOptions {
 
 /// Options for `resign` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct ResignOptions(PbResignRequest);
+pub struct ResignOptions {
+    req: PbResignRequest,
+    metadata: Metadata,
+}
 
 impl ResignOptions {
     #[inline]
     pub const fn new() -> Self {
-        Self(PbResignRequest { leader: None })
+        Self {
+            req: PbResignRequest { leader: None },
+            metadata: Metadata::new(),
+        }
     }
 
     /// The leadership to relinquish by resignation.
     #[inline]
     pub fn with_leader(mut self, leader: LeaderKey) -> Self {
-        self.0.leader = Some(leader.into());
+        self.req.leader = Some(leader.into());
+        self
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
         self
     }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbResignRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<ResignOptions> for PbResignRequest {
     #[inline]
     fn from(options: ResignOptions) -> Self {
-        options.0
+        options.req
     }
 }
 
@@ -476,12 +517,22 @@ impl From<&PbLeaderKey> for &LeaderKey {
     }
 }
 
+impl_with_compression!(ElectionClient);
+
 impl ElectionClient {
     /// Creates a election
     #[inline]
-    pub(crate) fn new(channel: Channel, auth_token: Option<Arc<HeaderValue>>) -> Self {
-        let inner = PbElectionClient::new(AuthService::new(channel, auth_token));
-        Self { inner }
+    pub(crate) fn new(
+        channel: Channel,
+        auth_token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+    ) -> Self {
+        let inner = PbElectionClient::new(AuthService::new(channel, auth_token, metadata));
+        Self {
+            #[cfg(feature = "compression")]
+            uncompressed: inner.clone(),
+            inner,
+        }
     }
 
     /// Puts a value as eligible for the election on the prefix key.
@@ -489,13 +540,14 @@ impl ElectionClient {
     /// same prefix, but only one can be the leader at a time.
     #[inline]
     pub async fn campaign(
-        &mut self,
+        &self,
         name: impl Into<Vec<u8>>,
         value: impl Into<Vec<u8>>,
         lease: i64,
     ) -> Result<CampaignResponse> {
         let resp = self
             .inner
+            .clone()
             .campaign(
                 CampaignOptions::new()
                     .with_name(name)
@@ -510,13 +562,14 @@ impl ElectionClient {
     /// Lets the leader announce a new value without another election.
     #[inline]
     pub async fn proclaim(
-        &mut self,
+        &self,
         value: impl Into<Vec<u8>>,
         options: Option<ProclaimOptions>,
     ) -> Result<ProclaimResponse> {
         let resp = self
             .inner
-            .proclaim(options.unwrap_or_default().with_value(value))
+            .clone()
+            .proclaim(options.unwrap_or_default().with_value(value).request()?)
             .await?
             .into_inner();
         Ok(ProclaimResponse::new(resp))
@@ -524,9 +577,10 @@ impl ElectionClient {
 
     /// Returns the leader value for the current election.
     #[inline]
-    pub async fn leader(&mut self, name: impl Into<Vec<u8>>) -> Result<LeaderResponse> {
+    pub async fn leader(&self, name: impl Into<Vec<u8>>) -> Result<LeaderResponse> {
         let resp = self
             .inner
+            .clone()
             .leader(LeaderOptions::new().with_name(name))
             .await?
             .into_inner();
@@ -536,9 +590,10 @@ impl ElectionClient {
     /// Returns a channel that reliably observes ordered leader proposals
     /// as GetResponse values on every current elected leader key.
     #[inline]
-    pub async fn observe(&mut self, name: impl Into<Vec<u8>>) -> Result<ObserveStream> {
+    pub async fn observe(&self, name: impl Into<Vec<u8>>) -> Result<ObserveStream> {
         let resp = self
             .inner
+            .clone()
             .observe(LeaderOptions::new().with_name(name))
             .await?
             .into_inner();
@@ -548,10 +603,11 @@ impl ElectionClient {
 
     /// Releases election leadership and then start a new election
     #[inline]
-    pub async fn resign(&mut self, option: Option<ResignOptions>) -> Result<ResignResponse> {
+    pub async fn resign(&self, option: Option<ResignOptions>) -> Result<ResignResponse> {
         let resp = self
             .inner
-            .resign(option.unwrap_or_default())
+            .clone()
+            .resign(option.unwrap_or_default().request()?)
             .await?
             .into_inner();
         Ok(ResignResponse::new(resp))
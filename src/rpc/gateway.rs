@@ -0,0 +1,1436 @@
+//! Client over the grpc-gateway of etcd, which serves the v3 API as JSON over HTTP/1.1.
+//!
+//! Only KV (including transactions), lease, watch and auth operations are supported.
+//! [`ConnectOptions::with_gateway`] sends them through the gateway from the service clients
+//! of [`Client`](crate::Client), while [`GatewayClient`] can be used on its own.
+//!
+//! The watches and lease keep alives of the gateway are not bidirectional streams:
+//! a watch is canceled by closing its response, and a lease is kept alive by one request
+//! per keep alive.
+
+#[cfg(feature = "tls")]
+mod https;
+
+use crate::client::ConnectOptions;
+use crate::error::{Error, Result};
+use crate::rpc::auth::{
+    AuthDisableResponse, AuthEnableResponse, AuthenticateResponse, Permission, RoleAddResponse,
+    RoleDeleteResponse, RoleGetResponse, RoleGrantPermissionResponse, RoleListResponse,
+    RoleRevokePermissionOptions, RoleRevokePermissionResponse, UserAddOptions, UserAddResponse,
+    UserChangePasswordResponse, UserDeleteResponse, UserGetResponse, UserGrantRoleResponse,
+    UserListResponse, UserRevokeRoleResponse,
+};
+use crate::rpc::kv::{
+    CompactionOptions, CompactionResponse, DeleteOptions, DeleteResponse, GetOptions, GetResponse,
+    PutOptions, PutResponse, Txn, TxnResponse,
+};
+use crate::rpc::lease::LeaseLeasesResponse;
+use crate::rpc::lease::{
+    LeaseGrantOptions, LeaseGrantResponse, LeaseKeepAliveResponse, LeaseRevokeResponse,
+    LeaseTimeToLiveOptions, LeaseTimeToLiveResponse,
+};
+use crate::rpc::pb::authpb::Permission as PbPermission;
+use crate::rpc::pb::etcdserverpb::compare::TargetUnion;
+use crate::rpc::pb::etcdserverpb::request_op::Request as PbTxnOp;
+use crate::rpc::pb::etcdserverpb::response_op::Response as PbTxnOpResponse;
+use crate::rpc::pb::etcdserverpb::watch_request::RequestUnion as WatchRequestUnion;
+use crate::rpc::pb::etcdserverpb::{
+    AuthDisableResponse as PbAuthDisableResponse, AuthEnableResponse as PbAuthEnableResponse,
+    AuthRoleAddResponse as PbAuthRoleAddResponse,
+    AuthRoleDeleteResponse as PbAuthRoleDeleteResponse,
+    AuthRoleGetResponse as PbAuthRoleGetResponse,
+    AuthRoleGrantPermissionResponse as PbAuthRoleGrantPermissionResponse,
+    AuthRoleListResponse as PbAuthRoleListResponse,
+    AuthRoleRevokePermissionRequest as PbAuthRoleRevokePermissionRequest,
+    AuthRoleRevokePermissionResponse as PbAuthRoleRevokePermissionResponse,
+    AuthUserAddRequest as PbAuthUserAddRequest, AuthUserAddResponse as PbAuthUserAddResponse,
+    AuthUserChangePasswordResponse as PbAuthUserChangePasswordResponse,
+    AuthUserDeleteResponse as PbAuthUserDeleteResponse,
+    AuthUserGetResponse as PbAuthUserGetResponse,
+    AuthUserGrantRoleResponse as PbAuthUserGrantRoleResponse,
+    AuthUserListResponse as PbAuthUserListResponse,
+    AuthUserRevokeRoleResponse as PbAuthUserRevokeRoleResponse,
+    AuthenticateResponse as PbAuthenticateResponse, CompactionRequest as PbCompactionRequest,
+    CompactionResponse as PbCompactionResponse, Compare as PbCompare,
+    DeleteRangeRequest as PbDeleteRequest, DeleteRangeResponse as PbDeleteResponse,
+    LeaseGrantRequest as PbLeaseGrantRequest, LeaseGrantResponse as PbLeaseGrantResponse,
+    LeaseKeepAliveResponse as PbLeaseKeepAliveResponse,
+    LeaseRevokeResponse as PbLeaseRevokeResponse,
+    LeaseTimeToLiveRequest as PbLeaseTimeToLiveRequest,
+    LeaseTimeToLiveResponse as PbLeaseTimeToLiveResponse, PutRequest as PbPutRequest,
+    PutResponse as PbPutResponse, RangeRequest as PbRangeRequest, RangeResponse as PbRangeResponse,
+    RequestOp as PbTxnRequestOp, ResponseHeader as PbResponseHeader, ResponseOp as PbTxnResponseOp,
+    TxnRequest as PbTxnRequest, TxnResponse as PbTxnResponse, WatchCreateRequest,
+    WatchResponse as PbWatchResponse,
+};
+use crate::rpc::pb::etcdserverpb::{
+    LeaseKeepAliveRequest as PbLeaseKeepAliveRequest, LeaseLeasesResponse as PbLeaseLeasesResponse,
+    LeaseStatus as PbLeaseStatus, WatchRequest,
+};
+use crate::rpc::pb::mvccpb::{Event as PbEvent, KeyValue as PbKeyValue};
+use crate::rpc::watch::{WatchOptions, WatchResponse};
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::body::HttpBody;
+#[cfg(not(feature = "tls"))]
+use hyper::client::HttpConnector;
+use hyper::{Body, Request, StatusCode};
+use serde_json::{json, Map, Value};
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc::Receiver;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Code, IntoRequest, Status};
+
+const HTTP_PREFIX: &str = "http://";
+#[cfg(feature = "tls")]
+const HTTPS_PREFIX: &str = "https://";
+
+/// Connects to the endpoints, over TLS for HTTPS ones.
+#[cfg(feature = "tls")]
+type Connector = https::HttpsConnector;
+#[cfg(not(feature = "tls"))]
+type Connector = HttpConnector;
+
+/// Encodes bytes like the JSON mapping of protobuf.
+#[inline]
+fn encode_bytes(bytes: &[u8]) -> Value {
+    Value::String(base64::encode(bytes))
+}
+
+/// Encodes a 64-bit integer like the JSON mapping of protobuf, as a string.
+#[inline]
+fn encode_i64(n: i64) -> Value {
+    Value::String(n.to_string())
+}
+
+/// Fields of a JSON object, where missing fields have their default value.
+struct Fields<'a>(Option<&'a Map<String, Value>>);
+
+impl<'a> Fields<'a> {
+    fn new(value: &'a Value) -> Self {
+        Self(value.as_object())
+    }
+
+    fn get(&self, name: &str) -> Option<&'a Value> {
+        self.0.and_then(|fields| fields.get(name))
+    }
+
+    fn object(&self, name: &str) -> Option<Fields<'a>> {
+        self.get(name).map(Fields::new)
+    }
+
+    fn array(&self, name: &str) -> &'a [Value] {
+        self.get(name)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn i64(&self, name: &str) -> Result<i64> {
+        match self.get(name) {
+            None | Some(Value::Null) => Ok(0),
+            Some(Value::String(s)) => s.parse().map_err(|_| invalid_field(name)),
+            Some(value) => value.as_i64().ok_or_else(|| invalid_field(name)),
+        }
+    }
+
+    fn u64(&self, name: &str) -> Result<u64> {
+        match self.get(name) {
+            None | Some(Value::Null) => Ok(0),
+            Some(Value::String(s)) => s.parse().map_err(|_| invalid_field(name)),
+            Some(value) => value.as_u64().ok_or_else(|| invalid_field(name)),
+        }
+    }
+
+    fn bool(&self, name: &str) -> bool {
+        self.get(name).and_then(Value::as_bool).unwrap_or_default()
+    }
+
+    fn string(&self, name: &str) -> String {
+        self.get(name)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn bytes(&self, name: &str) -> Result<Vec<u8>> {
+        decode_bytes(name, self.get(name))
+    }
+}
+
+#[inline]
+fn invalid_field(name: &str) -> Error {
+    Error::GatewayError(format!("invalid field {} in response", name))
+}
+
+fn decode_bytes(name: &str, value: Option<&Value>) -> Result<Vec<u8>> {
+    match value {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(s)) => base64::decode(s).map_err(|_| invalid_field(name)),
+        Some(_) => Err(invalid_field(name)),
+    }
+}
+
+fn decode_header(fields: &Fields) -> Result<Option<PbResponseHeader>> {
+    match fields.object("header") {
+        Some(header) => Ok(Some(PbResponseHeader {
+            cluster_id: header.u64("cluster_id")?,
+            member_id: header.u64("member_id")?,
+            revision: header.i64("revision")?,
+            raft_term: header.u64("raft_term")?,
+        })),
+        None => Ok(None),
+    }
+}
+
+fn decode_kv(fields: &Fields) -> Result<PbKeyValue> {
+    Ok(PbKeyValue {
+        key: fields.bytes("key")?,
+        create_revision: fields.i64("create_revision")?,
+        mod_revision: fields.i64("mod_revision")?,
+        version: fields.i64("version")?,
+        value: fields.bytes("value")?,
+        lease: fields.i64("lease")?,
+    })
+}
+
+fn decode_kvs(values: &[Value]) -> Result<Vec<PbKeyValue>> {
+    values
+        .iter()
+        .map(|value| decode_kv(&Fields::new(value)))
+        .collect()
+}
+
+fn decode_optional_kv(fields: &Fields, name: &str) -> Result<Option<PbKeyValue>> {
+    fields.object(name).map(|kv| decode_kv(&kv)).transpose()
+}
+
+fn decode_event(fields: &Fields) -> Result<PbEvent> {
+    let r#type = match fields.get("type") {
+        None | Some(Value::Null) => 0,
+        Some(Value::String(s)) if s == "PUT" => 0,
+        Some(Value::String(s)) if s == "DELETE" => 1,
+        Some(value) => value.as_i64().ok_or_else(|| invalid_field("type"))? as i32,
+    };
+    Ok(PbEvent {
+        r#type,
+        kv: decode_optional_kv(fields, "kv")?,
+        prev_kv: decode_optional_kv(fields, "prev_kv")?,
+    })
+}
+
+fn decode_watch_response(fields: &Fields) -> Result<PbWatchResponse> {
+    Ok(PbWatchResponse {
+        header: decode_header(fields)?,
+        watch_id: fields.i64("watch_id")?,
+        created: fields.bool("created"),
+        canceled: fields.bool("canceled"),
+        compact_revision: fields.i64("compact_revision")?,
+        cancel_reason: fields.string("cancel_reason"),
+        fragment: fields.bool("fragment"),
+        events: fields
+            .array("events")
+            .iter()
+            .map(|event| decode_event(&Fields::new(event)))
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn encode_put(req: &PbPutRequest) -> Value {
+    json!({
+        "key": encode_bytes(&req.key),
+        "value": encode_bytes(&req.value),
+        "lease": encode_i64(req.lease),
+        "prev_kv": req.prev_kv,
+        "ignore_value": req.ignore_value,
+        "ignore_lease": req.ignore_lease,
+    })
+}
+
+fn encode_range(req: &PbRangeRequest) -> Value {
+    json!({
+        "key": encode_bytes(&req.key),
+        "range_end": encode_bytes(&req.range_end),
+        "limit": encode_i64(req.limit),
+        "revision": encode_i64(req.revision),
+        "sort_order": req.sort_order,
+        "sort_target": req.sort_target,
+        "serializable": req.serializable,
+        "keys_only": req.keys_only,
+        "count_only": req.count_only,
+        "min_mod_revision": encode_i64(req.min_mod_revision),
+        "max_mod_revision": encode_i64(req.max_mod_revision),
+        "min_create_revision": encode_i64(req.min_create_revision),
+        "max_create_revision": encode_i64(req.max_create_revision),
+    })
+}
+
+fn encode_delete(req: &PbDeleteRequest) -> Value {
+    json!({
+        "key": encode_bytes(&req.key),
+        "range_end": encode_bytes(&req.range_end),
+        "prev_kv": req.prev_kv,
+    })
+}
+
+fn encode_compare(compare: &PbCompare) -> Value {
+    let mut value = json!({
+        "result": compare.result,
+        "target": compare.target,
+        "key": encode_bytes(&compare.key),
+        "range_end": encode_bytes(&compare.range_end),
+    });
+    let (name, target) = match &compare.target_union {
+        Some(TargetUnion::Version(version)) => ("version", encode_i64(*version)),
+        Some(TargetUnion::CreateRevision(revision)) => ("create_revision", encode_i64(*revision)),
+        Some(TargetUnion::ModRevision(revision)) => ("mod_revision", encode_i64(*revision)),
+        Some(TargetUnion::Value(v)) => ("value", encode_bytes(v)),
+        Some(TargetUnion::Lease(lease)) => ("lease", encode_i64(*lease)),
+        None => return value,
+    };
+    value[name] = target;
+    value
+}
+
+fn encode_txn_op(op: &PbTxnRequestOp) -> Value {
+    match &op.request {
+        Some(PbTxnOp::RequestRange(req)) => json!({ "request_range": encode_range(req) }),
+        Some(PbTxnOp::RequestPut(req)) => json!({ "request_put": encode_put(req) }),
+        Some(PbTxnOp::RequestDeleteRange(req)) => {
+            json!({ "request_delete_range": encode_delete(req) })
+        }
+        Some(PbTxnOp::RequestTxn(req)) => json!({ "request_txn": encode_txn(req) }),
+        None => json!({}),
+    }
+}
+
+fn encode_txn(req: &PbTxnRequest) -> Value {
+    json!({
+        "compare": req.compare.iter().map(encode_compare).collect::<Vec<_>>(),
+        "success": req.success.iter().map(encode_txn_op).collect::<Vec<_>>(),
+        "failure": req.failure.iter().map(encode_txn_op).collect::<Vec<_>>(),
+    })
+}
+
+fn encode_permission(perm: &PbPermission) -> Value {
+    json!({
+        "permType": perm.perm_type,
+        "key": encode_bytes(&perm.key),
+        "range_end": encode_bytes(&perm.range_end),
+    })
+}
+
+fn decode_put_response(fields: &Fields) -> Result<PbPutResponse> {
+    Ok(PbPutResponse {
+        header: decode_header(fields)?,
+        prev_kv: decode_optional_kv(fields, "prev_kv")?,
+    })
+}
+
+fn decode_range_response(fields: &Fields) -> Result<PbRangeResponse> {
+    Ok(PbRangeResponse {
+        header: decode_header(fields)?,
+        kvs: decode_kvs(fields.array("kvs"))?,
+        more: fields.bool("more"),
+        count: fields.i64("count")?,
+    })
+}
+
+fn decode_delete_response(fields: &Fields) -> Result<PbDeleteResponse> {
+    Ok(PbDeleteResponse {
+        header: decode_header(fields)?,
+        deleted: fields.i64("deleted")?,
+        prev_kvs: decode_kvs(fields.array("prev_kvs"))?,
+    })
+}
+
+fn decode_txn_op_response(fields: &Fields) -> Result<PbTxnResponseOp> {
+    let response = if let Some(resp) = fields.object("response_range") {
+        Some(PbTxnOpResponse::ResponseRange(decode_range_response(
+            &resp,
+        )?))
+    } else if let Some(resp) = fields.object("response_put") {
+        Some(PbTxnOpResponse::ResponsePut(decode_put_response(&resp)?))
+    } else if let Some(resp) = fields.object("response_delete_range") {
+        Some(PbTxnOpResponse::ResponseDeleteRange(
+            decode_delete_response(&resp)?,
+        ))
+    } else if let Some(resp) = fields.object("response_txn") {
+        Some(PbTxnOpResponse::ResponseTxn(decode_txn_response(&resp)?))
+    } else {
+        None
+    };
+    Ok(PbTxnResponseOp { response })
+}
+
+fn decode_txn_response(fields: &Fields) -> Result<PbTxnResponse> {
+    Ok(PbTxnResponse {
+        header: decode_header(fields)?,
+        succeeded: fields.bool("succeeded"),
+        responses: fields
+            .array("responses")
+            .iter()
+            .map(|resp| decode_txn_op_response(&Fields::new(resp)))
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn decode_permission(fields: &Fields) -> Result<PbPermission> {
+    let perm_type = match fields.get("permType") {
+        None | Some(Value::Null) => 0,
+        Some(Value::String(s)) if s == "READ" => 0,
+        Some(Value::String(s)) if s == "WRITE" => 1,
+        Some(Value::String(s)) if s == "READWRITE" => 2,
+        Some(value) => value.as_i64().ok_or_else(|| invalid_field("permType"))? as i32,
+    };
+    Ok(PbPermission {
+        perm_type,
+        key: fields.bytes("key")?,
+        range_end: fields.bytes("range_end")?,
+    })
+}
+
+fn decode_strings(fields: &Fields, name: &str) -> Vec<String> {
+    fields
+        .array(name)
+        .iter()
+        .filter_map(Value::as_str)
+        .map(String::from)
+        .collect()
+}
+
+/// Converts an error returned by the gateway into a gRPC status.
+fn decode_error(status: StatusCode, value: &Value) -> Error {
+    let fields = Fields::new(value);
+    match fields.get("code").and_then(Value::as_i64) {
+        Some(code) => {
+            let message = match fields.get("message").and_then(Value::as_str) {
+                Some(message) => message.to_string(),
+                None => fields.string("error"),
+            };
+            Error::GRpcStatus(Status::new(Code::from_i32(code as i32), message))
+        }
+        None => Error::GatewayError(format!("unexpected HTTP status {}", status)),
+    }
+}
+
+/// Client of the etcd grpc-gateway, for environments where HTTP/2 is not available.
+///
+/// The operations take the same options and return the same responses as their
+/// counterparts of [`Client`](crate::Client). Requests are sent to the first endpoint
+/// that accepts the connection, starting after the endpoint of the previous request.
+#[derive(Clone)]
+pub struct GatewayClient {
+    http: hyper::Client<Connector>,
+    endpoints: Arc<Vec<String>>,
+    next: Arc<AtomicUsize>,
+    token: Option<String>,
+}
+
+impl Debug for GatewayClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GatewayClient")
+            .field("endpoints", &self.endpoints)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl GatewayClient {
+    /// Connects to the grpc-gateway of `etcd` servers at the given `endpoints`.
+    ///
+    /// Endpoints without a scheme use HTTP. HTTPS endpoints require the `tls` feature, and are
+    /// verified with the TLS files of the options, like [`ConnectOptions::with_ca_cert_file`],
+    /// authenticating with [`ConnectOptions::with_client_cert_auth`] if set.
+    /// The user set by [`ConnectOptions::with_user`] is authenticated. Connecting fails if
+    /// the options set metadata, require a leader, limit requests, compress messages or
+    /// discover the endpoints, which the gateway does not support.
+    pub async fn connect<E: AsRef<str>, S: AsRef<[E]>>(
+        endpoints: S,
+        options: Option<ConnectOptions>,
+    ) -> Result<Self> {
+        if let Some(options) = &options {
+            options.check_gateway()?;
+        }
+
+        let endpoints = endpoints
+            .as_ref()
+            .iter()
+            .map(|e| {
+                let e = e.as_ref().trim_end_matches('/');
+                #[cfg(feature = "tls")]
+                let supported = e.starts_with(HTTP_PREFIX) || e.starts_with(HTTPS_PREFIX);
+                #[cfg(not(feature = "tls"))]
+                let supported = e.starts_with(HTTP_PREFIX);
+                if supported {
+                    Ok(e.to_string())
+                } else if e.contains("://") {
+                    Err(Error::InvalidArgs(format!(
+                        "unsupported gateway endpoint {}, only HTTP and HTTPS are supported",
+                        e
+                    )))
+                } else {
+                    Ok(HTTP_PREFIX.to_owned() + e)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if endpoints.is_empty() {
+            return Err(Error::InvalidArgs(String::from("empty endpoints")));
+        }
+
+        #[cfg(feature = "tls")]
+        let connector = if endpoints.iter().any(|e| e.starts_with(HTTPS_PREFIX)) {
+            let (config, server_name) = match &options {
+                Some(options) => options.gateway_tls().await?,
+                None => ConnectOptions::new().gateway_tls().await?,
+            };
+            https::HttpsConnector::new(config, server_name)
+        } else {
+            https::HttpsConnector::new(rustls::ClientConfig::new(), None)
+        };
+        #[cfg(not(feature = "tls"))]
+        let connector = HttpConnector::new();
+
+        let mut client = Self {
+            http: hyper::Client::builder().build(connector),
+            endpoints: Arc::new(endpoints),
+            next: Arc::new(AtomicUsize::new(0)),
+            token: None,
+        };
+
+        if let Some((name, password)) = options.as_ref().and_then(ConnectOptions::user) {
+            let resp = client
+                .call(
+                    "/v3/auth/authenticate",
+                    json!({ "name": name, "password": password }),
+                )
+                .await?;
+            client.token = Some(Fields::new(&resp).string("token"));
+        }
+        Ok(client)
+    }
+
+    /// Posts `body` to `path`, failing over to the next endpoint on connection errors.
+    async fn post(&self, path: &str, body: &Value) -> Result<hyper::Response<Body>> {
+        let body = body.to_string();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut last_error = None;
+        for i in 0..self.endpoints.len() {
+            let endpoint = &self.endpoints[(start + i) % self.endpoints.len()];
+            let mut req = Request::post(format!("{}{}", endpoint, path))
+                .header(CONTENT_TYPE, "application/json");
+            if let Some(token) = &self.token {
+                req = req.header(AUTHORIZATION, token.as_str());
+            }
+            let req = req
+                .body(Body::from(body.clone()))
+                .map_err(|e| Error::GatewayError(e.to_string()))?;
+
+            match self.http.request(req).await {
+                Ok(resp) => return Ok(resp),
+                Err(e) if e.is_connect() => last_error = Some(e),
+                Err(e) => return Err(Error::GatewayError(e.to_string())),
+            }
+        }
+        Err(Error::GatewayError(format!(
+            "no endpoint available: {}",
+            last_error.map(|e| e.to_string()).unwrap_or_default()
+        )))
+    }
+
+    /// Posts `body` to `path` and returns the JSON response.
+    async fn call(&self, path: &str, body: Value) -> Result<Value> {
+        let resp = self.post(path, &body).await?;
+        let status = resp.status();
+        let bytes = hyper::body::to_bytes(resp.into_body())
+            .await
+            .map_err(|e| Error::GatewayError(e.to_string()))?;
+        let value = serde_json::from_slice::<Value>(&bytes)
+            .map_err(|e| Error::GatewayError(format!("invalid response: {}", e)))?;
+
+        if !status.is_success() {
+            return Err(decode_error(status, &value));
+        }
+        Ok(value)
+    }
+
+    /// The authentication token of the user of the options, if any.
+    #[inline]
+    pub(crate) fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Puts the given key into the key-value store.
+    pub async fn put(
+        &self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+        options: Option<PutOptions>,
+    ) -> Result<PutResponse> {
+        let req = PbPutRequest::from(options.unwrap_or_default().with_kv(key, value));
+        let resp = self.call("/v3/kv/put", encode_put(&req)).await?;
+        Ok(PutResponse::new(decode_put_response(&Fields::new(&resp))?))
+    }
+
+    /// Gets the key or a range of keys from the store.
+    pub async fn get(
+        &self,
+        key: impl Into<Vec<u8>>,
+        options: Option<GetOptions>,
+    ) -> Result<GetResponse> {
+        let req = PbRangeRequest::from(options.unwrap_or_default().with_key(key));
+        let resp = self.call("/v3/kv/range", encode_range(&req)).await?;
+        Ok(GetResponse::new(decode_range_response(&Fields::new(
+            &resp,
+        ))?))
+    }
+
+    /// Deletes the given key or a range of keys from the key-value store.
+    pub async fn delete(
+        &self,
+        key: impl Into<Vec<u8>>,
+        options: Option<DeleteOptions>,
+    ) -> Result<DeleteResponse> {
+        let req = PbDeleteRequest::from(options.unwrap_or_default().with_key(key));
+        let resp = self.call("/v3/kv/deleterange", encode_delete(&req)).await?;
+        Ok(DeleteResponse::new(decode_delete_response(&Fields::new(
+            &resp,
+        ))?))
+    }
+
+    /// Compacts the event history in the etcd key-value store.
+    pub async fn compact(
+        &self,
+        revision: i64,
+        options: Option<CompactionOptions>,
+    ) -> Result<CompactionResponse> {
+        let req: PbCompactionRequest = options
+            .unwrap_or_default()
+            .with_revision(revision)
+            .into_request()
+            .into_inner();
+        let resp = self
+            .call(
+                "/v3/kv/compaction",
+                json!({
+                    "revision": encode_i64(req.revision),
+                    "physical": req.physical,
+                }),
+            )
+            .await?;
+
+        Ok(CompactionResponse::new(PbCompactionResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Processes multiple operations in a single transaction.
+    pub async fn txn(&self, txn: Txn) -> Result<TxnResponse> {
+        let req = PbTxnRequest::from(txn);
+        let resp = self.call("/v3/kv/txn", encode_txn(&req)).await?;
+        Ok(TxnResponse::new(decode_txn_response(&Fields::new(&resp))?))
+    }
+
+    /// Creates a lease which expires if the server does not receive a keepAlive
+    /// within a given time to live period.
+    pub async fn lease_grant(
+        &self,
+        ttl: i64,
+        options: Option<LeaseGrantOptions>,
+    ) -> Result<LeaseGrantResponse> {
+        let req = PbLeaseGrantRequest::from(options.unwrap_or_default().with_ttl(ttl));
+        let resp = self
+            .call(
+                "/v3/lease/grant",
+                json!({ "TTL": encode_i64(req.ttl), "ID": encode_i64(req.id) }),
+            )
+            .await?;
+
+        let fields = Fields::new(&resp);
+        Ok(LeaseGrantResponse::new(PbLeaseGrantResponse {
+            header: decode_header(&fields)?,
+            id: fields.i64("ID")?,
+            ttl: fields.i64("TTL")?,
+            error: fields.string("error"),
+        }))
+    }
+
+    /// Revokes a lease.
+    pub async fn lease_revoke(&self, id: i64) -> Result<LeaseRevokeResponse> {
+        let resp = self
+            .call("/v3/lease/revoke", json!({ "ID": encode_i64(id) }))
+            .await?;
+
+        Ok(LeaseRevokeResponse::new(PbLeaseRevokeResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Keeps the lease alive once, returning the new time to live.
+    ///
+    /// Unlike [`Client::lease_keep_alive`](crate::Client::lease_keep_alive), no stream is kept
+    /// open, so this has to be called periodically.
+    #[inline]
+    pub async fn lease_keep_alive(&self, id: i64) -> Result<LeaseKeepAliveResponse> {
+        Ok(LeaseKeepAliveResponse::new(self.keep_alive_once(id).await?))
+    }
+
+    /// Keeps the lease alive once.
+    pub(crate) async fn keep_alive_once(&self, id: i64) -> Result<PbLeaseKeepAliveResponse> {
+        let resp = self
+            .call("/v3/lease/keepalive", json!({ "ID": encode_i64(id) }))
+            .await?;
+
+        // the gateway wraps the messages of streams
+        let fields = Fields::new(&resp);
+        let result = fields
+            .object("result")
+            .ok_or_else(|| invalid_field("result"))?;
+        Ok(PbLeaseKeepAliveResponse {
+            header: decode_header(&result)?,
+            id: result.i64("ID")?,
+            ttl: result.i64("TTL")?,
+        })
+    }
+
+    /// Retrieves lease information.
+    pub async fn lease_time_to_live(
+        &self,
+        id: i64,
+        options: Option<LeaseTimeToLiveOptions>,
+    ) -> Result<LeaseTimeToLiveResponse> {
+        let req = PbLeaseTimeToLiveRequest::from(options.unwrap_or_default().with_id(id));
+        let resp = self
+            .call(
+                "/v3/lease/timetolive",
+                json!({ "ID": encode_i64(req.id), "keys": req.keys }),
+            )
+            .await?;
+
+        let fields = Fields::new(&resp);
+        Ok(LeaseTimeToLiveResponse::new(PbLeaseTimeToLiveResponse {
+            header: decode_header(&fields)?,
+            id: fields.i64("ID")?,
+            ttl: fields.i64("TTL")?,
+            granted_ttl: fields.i64("grantedTTL")?,
+            keys: fields
+                .array("keys")
+                .iter()
+                .map(|key| decode_bytes("keys", Some(key)))
+                .collect::<Result<_>>()?,
+        }))
+    }
+
+    /// Lists all existing leases.
+    pub async fn lease_leases(&self) -> Result<LeaseLeasesResponse> {
+        let resp = self.call("/v3/lease/leases", json!({})).await?;
+
+        let fields = Fields::new(&resp);
+        Ok(LeaseLeasesResponse::new(PbLeaseLeasesResponse {
+            header: decode_header(&fields)?,
+            leases: fields
+                .array("leases")
+                .iter()
+                .map(|lease| {
+                    Ok(PbLeaseStatus {
+                        id: Fields::new(lease).i64("ID")?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        }))
+    }
+
+    /// Enables authentication.
+    pub async fn auth_enable(&self) -> Result<AuthEnableResponse> {
+        let resp = self.call("/v3/auth/enable", json!({})).await?;
+        Ok(AuthEnableResponse::new(PbAuthEnableResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Disables authentication.
+    pub async fn auth_disable(&self) -> Result<AuthDisableResponse> {
+        let resp = self.call("/v3/auth/disable", json!({})).await?;
+        Ok(AuthDisableResponse::new(PbAuthDisableResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Processes an authenticate request.
+    ///
+    /// The returned token is not used by this client, which authenticates the user
+    /// of the options given to [`GatewayClient::connect`].
+    pub async fn authenticate(
+        &self,
+        name: String,
+        password: String,
+    ) -> Result<AuthenticateResponse> {
+        let resp = self
+            .call(
+                "/v3/auth/authenticate",
+                json!({ "name": name, "password": password }),
+            )
+            .await?;
+
+        let fields = Fields::new(&resp);
+        Ok(AuthenticateResponse::new(PbAuthenticateResponse {
+            header: decode_header(&fields)?,
+            token: fields.string("token"),
+        }))
+    }
+
+    /// Adds role.
+    pub async fn role_add(&self, name: impl Into<String>) -> Result<RoleAddResponse> {
+        let resp = self
+            .call("/v3/auth/role/add", json!({ "name": name.into() }))
+            .await?;
+        Ok(RoleAddResponse::new(PbAuthRoleAddResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Deletes role.
+    pub async fn role_delete(&self, name: impl Into<String>) -> Result<RoleDeleteResponse> {
+        let resp = self
+            .call("/v3/auth/role/delete", json!({ "role": name.into() }))
+            .await?;
+        Ok(RoleDeleteResponse::new(PbAuthRoleDeleteResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Gets role.
+    pub async fn role_get(&self, name: impl Into<String>) -> Result<RoleGetResponse> {
+        let resp = self
+            .call("/v3/auth/role/get", json!({ "role": name.into() }))
+            .await?;
+
+        let fields = Fields::new(&resp);
+        Ok(RoleGetResponse::new(PbAuthRoleGetResponse {
+            header: decode_header(&fields)?,
+            perm: fields
+                .array("perm")
+                .iter()
+                .map(|perm| decode_permission(&Fields::new(perm)))
+                .collect::<Result<_>>()?,
+        }))
+    }
+
+    /// Lists role.
+    pub async fn role_list(&self) -> Result<RoleListResponse> {
+        let resp = self.call("/v3/auth/role/list", json!({})).await?;
+
+        let fields = Fields::new(&resp);
+        Ok(RoleListResponse::new(PbAuthRoleListResponse {
+            header: decode_header(&fields)?,
+            roles: decode_strings(&fields, "roles"),
+        }))
+    }
+
+    /// Grants role permission.
+    pub async fn role_grant_permission(
+        &self,
+        name: impl Into<String>,
+        perm: Permission,
+    ) -> Result<RoleGrantPermissionResponse> {
+        let perm = PbPermission::from(perm);
+        let resp = self
+            .call(
+                "/v3/auth/role/grant",
+                json!({ "name": name.into(), "perm": encode_permission(&perm) }),
+            )
+            .await?;
+        Ok(RoleGrantPermissionResponse::new(
+            PbAuthRoleGrantPermissionResponse {
+                header: decode_header(&Fields::new(&resp))?,
+            },
+        ))
+    }
+
+    /// Revokes role permission.
+    pub async fn role_revoke_permission(
+        &self,
+        name: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        options: Option<RoleRevokePermissionOptions>,
+    ) -> Result<RoleRevokePermissionResponse> {
+        let req = PbAuthRoleRevokePermissionRequest::from(
+            options
+                .unwrap_or_default()
+                .with_name(name.into())
+                .with_key(key.into()),
+        );
+        let resp = self
+            .call(
+                "/v3/auth/role/revoke",
+                json!({
+                    "role": req.role,
+                    "key": encode_bytes(&req.key),
+                    "range_end": encode_bytes(&req.range_end),
+                }),
+            )
+            .await?;
+        Ok(RoleRevokePermissionResponse::new(
+            PbAuthRoleRevokePermissionResponse {
+                header: decode_header(&Fields::new(&resp))?,
+            },
+        ))
+    }
+
+    /// Adds user.
+    pub async fn user_add(
+        &self,
+        name: impl Into<String>,
+        password: impl Into<String>,
+        options: Option<UserAddOptions>,
+    ) -> Result<UserAddResponse> {
+        let req = PbAuthUserAddRequest::from(
+            options
+                .unwrap_or_default()
+                .with_name(name.into())
+                .with_pwd(password.into()),
+        );
+        let no_password = req.options.unwrap_or_default().no_password;
+        let resp = self
+            .call(
+                "/v3/auth/user/add",
+                json!({
+                    "name": req.name,
+                    "password": req.password,
+                    "options": { "no_password": no_password },
+                }),
+            )
+            .await?;
+        Ok(UserAddResponse::new(PbAuthUserAddResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Gets user.
+    pub async fn user_get(&self, name: impl Into<String>) -> Result<UserGetResponse> {
+        let resp = self
+            .call("/v3/auth/user/get", json!({ "name": name.into() }))
+            .await?;
+
+        let fields = Fields::new(&resp);
+        Ok(UserGetResponse::new(PbAuthUserGetResponse {
+            header: decode_header(&fields)?,
+            roles: decode_strings(&fields, "roles"),
+        }))
+    }
+
+    /// Lists user.
+    pub async fn user_list(&self) -> Result<UserListResponse> {
+        let resp = self.call("/v3/auth/user/list", json!({})).await?;
+
+        let fields = Fields::new(&resp);
+        Ok(UserListResponse::new(PbAuthUserListResponse {
+            header: decode_header(&fields)?,
+            users: decode_strings(&fields, "users"),
+        }))
+    }
+
+    /// Deletes user.
+    pub async fn user_delete(&self, name: impl Into<String>) -> Result<UserDeleteResponse> {
+        let resp = self
+            .call("/v3/auth/user/delete", json!({ "name": name.into() }))
+            .await?;
+        Ok(UserDeleteResponse::new(PbAuthUserDeleteResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Changes user's password.
+    pub async fn user_change_password(
+        &self,
+        name: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<UserChangePasswordResponse> {
+        let resp = self
+            .call(
+                "/v3/auth/user/changepw",
+                json!({ "name": name.into(), "password": password.into() }),
+            )
+            .await?;
+        Ok(UserChangePasswordResponse::new(
+            PbAuthUserChangePasswordResponse {
+                header: decode_header(&Fields::new(&resp))?,
+            },
+        ))
+    }
+
+    /// Grants role for an user.
+    pub async fn user_grant_role(
+        &self,
+        name: impl Into<String>,
+        role: impl Into<String>,
+    ) -> Result<UserGrantRoleResponse> {
+        let resp = self
+            .call(
+                "/v3/auth/user/grant",
+                json!({ "user": name.into(), "role": role.into() }),
+            )
+            .await?;
+        Ok(UserGrantRoleResponse::new(PbAuthUserGrantRoleResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Revokes role for an user.
+    pub async fn user_revoke_role(
+        &self,
+        name: impl Into<String>,
+        role: impl Into<String>,
+    ) -> Result<UserRevokeRoleResponse> {
+        let resp = self
+            .call(
+                "/v3/auth/user/revoke",
+                json!({ "name": name.into(), "role": role.into() }),
+            )
+            .await?;
+        Ok(UserRevokeRoleResponse::new(PbAuthUserRevokeRoleResponse {
+            header: decode_header(&Fields::new(&resp))?,
+        }))
+    }
+
+    /// Watches for events happening or that have happened.
+    ///
+    /// The watch is canceled by dropping the returned stream.
+    pub async fn watch(
+        &self,
+        key: impl Into<Vec<u8>>,
+        options: Option<WatchOptions>,
+    ) -> Result<GatewayWatchStream> {
+        let req = WatchCreateRequest::from(options.unwrap_or_default().with_key(key));
+        self.open_watch(&req).await
+    }
+
+    /// Opens a watch stream creating the watch of `req`.
+    pub(crate) async fn open_watch(&self, req: &WatchCreateRequest) -> Result<GatewayWatchStream> {
+        let body = json!({
+            "create_request": {
+                "key": encode_bytes(&req.key),
+                "range_end": encode_bytes(&req.range_end),
+                "start_revision": encode_i64(req.start_revision),
+                "progress_notify": req.progress_notify,
+                "filters": req.filters,
+                "prev_kv": req.prev_kv,
+                "watch_id": encode_i64(req.watch_id),
+                "fragment": req.fragment,
+            }
+        });
+        let resp = self.post("/v3/watch", &body).await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let bytes = hyper::body::to_bytes(resp.into_body())
+                .await
+                .map_err(|e| Error::GatewayError(e.to_string()))?;
+            let value = serde_json::from_slice::<Value>(&bytes).unwrap_or_default();
+            return Err(decode_error(status, &value));
+        }
+
+        Ok(GatewayWatchStream {
+            body: resp.into_body(),
+            buf: Vec::new(),
+        })
+    }
+}
+
+/// The stream of watch responses of [`GatewayClient::watch`], read from a chunked response.
+#[derive(Debug)]
+pub struct GatewayWatchStream {
+    body: Body,
+    buf: Vec<u8>,
+}
+
+impl GatewayWatchStream {
+    /// Fetches the next message from this stream.
+    #[inline]
+    pub async fn message(&mut self) -> Result<Option<WatchResponse>> {
+        self.next().await.transpose()
+    }
+
+    /// Polls the next message of the stream.
+    fn poll_message(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<PbWatchResponse>>> {
+        loop {
+            // the messages are delimited by newlines
+            if let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+                let line = self.buf.drain(..=pos).collect::<Vec<_>>();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                return Poll::Ready(Some(decode_watch_message(&line)));
+            }
+
+            match Pin::new(&mut self.body).poll_data(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.buf.extend_from_slice(&chunk),
+                Poll::Ready(Some(Err(e))) => {
+                    return Poll::Ready(Some(Err(Error::GatewayError(e.to_string()))))
+                }
+                Poll::Ready(None) if self.buf.iter().all(u8::is_ascii_whitespace) => {
+                    return Poll::Ready(None)
+                }
+                Poll::Ready(None) => {
+                    let line = std::mem::take(&mut self.buf);
+                    return Poll::Ready(Some(decode_watch_message(&line)));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Stream for GatewayWatchStream {
+    type Item = Result<WatchResponse>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .poll_message(cx)
+            .map(|resp| resp.map(|resp| resp.map(WatchResponse::new)))
+    }
+}
+
+/// Decodes a message of a watch stream.
+fn decode_watch_message(line: &[u8]) -> Result<PbWatchResponse> {
+    let value = serde_json::from_slice::<Value>(line)
+        .map_err(|e| Error::GatewayError(format!("invalid watch response: {}", e)))?;
+    let fields = Fields::new(&value);
+    if let Some(error) = fields.get("error") {
+        return Err(decode_stream_error(error));
+    }
+    let result = fields
+        .object("result")
+        .ok_or_else(|| invalid_field("result"))?;
+    decode_watch_response(&result)
+}
+
+/// Decodes the error ending a stream, like
+/// `{"grpc_code":11,"http_code":400,"message":"...","http_status":"Bad Request"}`.
+fn decode_stream_error(value: &Value) -> Error {
+    let fields = Fields::new(value);
+    let message = fields.string("message");
+    match fields.get("grpc_code").and_then(Value::as_i64) {
+        Some(code) => Error::GRpcStatus(Status::new(Code::from_i32(code as i32), message)),
+        None => Error::GatewayError(format!("watch stream error: {}", message)),
+    }
+}
+
+/// A watch of a [`WatchStream`](crate::WatchStream) over the gateway, applying the requests
+/// of its [`Watcher`](crate::Watcher).
+#[derive(Debug)]
+pub struct GatewayWatch {
+    /// `None` once canceled
+    stream: Option<GatewayWatchStream>,
+    /// `None` once the watcher is dropped
+    requests: Option<Receiver<WatchRequest>>,
+}
+
+impl GatewayWatch {
+    /// Opens the watch created by `request`, returning it with its watch id.
+    pub(crate) async fn open(
+        gateway: &GatewayClient,
+        request: &WatchRequest,
+        requests: Receiver<WatchRequest>,
+    ) -> Result<(Self, i64)> {
+        let create = match &request.request_union {
+            Some(WatchRequestUnion::CreateRequest(create)) => create,
+            _ => {
+                return Err(Error::InvalidArgs(String::from(
+                    "not a create watch request",
+                )))
+            }
+        };
+        let mut stream = gateway.open_watch(create).await?;
+
+        let watch_id = match stream.message().await? {
+            Some(resp) => {
+                assert!(resp.created(), "not a create watch response");
+                resp.watch_id()
+            }
+            None => {
+                return Err(Error::WatchError("failed to create watch".to_string()));
+            }
+        };
+        Ok((
+            Self {
+                stream: Some(stream),
+                requests: Some(requests),
+            },
+            watch_id,
+        ))
+    }
+
+    /// Polls the next message of the watch.
+    ///
+    /// A cancel request closes the stream after a canceled response, like etcd.
+    /// Progress requests cannot be sent through the gateway and fail.
+    pub(crate) fn poll_message(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<PbWatchResponse>>> {
+        while let Some(requests) = &mut self.requests {
+            match requests.poll_recv(cx) {
+                Poll::Ready(Some(req)) => match req.request_union {
+                    Some(WatchRequestUnion::CancelRequest(cancel)) => {
+                        self.stream = None;
+                        self.requests = None;
+                        return Poll::Ready(Some(Ok(PbWatchResponse {
+                            watch_id: cancel.watch_id,
+                            canceled: true,
+                            ..PbWatchResponse::default()
+                        })));
+                    }
+                    Some(WatchRequestUnion::ProgressRequest(_)) => {
+                        return Poll::Ready(Some(Err(Error::GatewayError(String::from(
+                            "progress requests are not supported by the gateway",
+                        )))));
+                    }
+                    _ => {}
+                },
+                Poll::Ready(None) => self.requests = None,
+                Poll::Pending => break,
+            }
+        }
+
+        match &mut self.stream {
+            Some(stream) => stream.poll_message(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+type KeepAliveFuture = Pin<Box<dyn Future<Output = Result<PbLeaseKeepAliveResponse>> + Send>>;
+
+/// Keep alives of a [`LeaseKeepAliveStream`](crate::LeaseKeepAliveStream) over the gateway,
+/// one request for each keep alive of its [`LeaseKeeper`](crate::LeaseKeeper).
+pub struct GatewayKeepAlive {
+    gateway: GatewayClient,
+    requests: Receiver<PbLeaseKeepAliveRequest>,
+    request: Option<KeepAliveFuture>,
+}
+
+impl GatewayKeepAlive {
+    #[inline]
+    pub(crate) fn new(gateway: GatewayClient, requests: Receiver<PbLeaseKeepAliveRequest>) -> Self {
+        Self {
+            gateway,
+            requests,
+            request: None,
+        }
+    }
+
+    /// Polls the response of the next keep alive, ending once the keeper is dropped.
+    pub(crate) fn poll_message(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<PbLeaseKeepAliveResponse>>> {
+        loop {
+            if let Some(request) = &mut self.request {
+                let resp = match request.as_mut().poll(cx) {
+                    Poll::Ready(resp) => resp,
+                    Poll::Pending => return Poll::Pending,
+                };
+                self.request = None;
+                return Poll::Ready(Some(resp));
+            }
+
+            match self.requests.poll_recv(cx) {
+                Poll::Ready(Some(req)) => {
+                    let gateway = self.gateway.clone();
+                    self.request = Some(Box::pin(
+                        async move { gateway.keep_alive_once(req.id).await },
+                    ));
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Debug for GatewayKeepAlive {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GatewayKeepAlive")
+            .field("gateway", &self.gateway)
+            .field("requests", &self.requests)
+            .field("pending", &self.request.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::TEST_ENDPOINTS;
+    use crate::rpc::kv::{Compare, CompareOp, TxnOp};
+    use crate::Client;
+
+    #[test]
+    fn test_decode_watch_response() -> Result<()> {
+        let value = json!({
+            "header": { "cluster_id": "14841639068965178418", "revision": "8" },
+            "watch_id": "1",
+            "events": [
+                { "kv": { "key": "Zm9v", "value": "YmFy", "mod_revision": "8" } },
+                { "type": "DELETE", "kv": { "key": "Zm9v" } },
+            ],
+        });
+        let resp = decode_watch_response(&Fields::new(&value))?;
+        assert_eq!(resp.header.unwrap().cluster_id, 14841639068965178418);
+        assert_eq!(resp.watch_id, 1);
+        assert!(!resp.created);
+        assert_eq!(resp.events.len(), 2);
+        assert_eq!(resp.events[0].r#type, 0);
+        let kv = resp.events[0].kv.as_ref().unwrap();
+        assert_eq!(kv.key, b"foo");
+        assert_eq!(kv.value, b"bar");
+        assert_eq!(kv.mod_revision, 8);
+        assert_eq!(resp.events[1].r#type, 1);
+
+        let error = decode_error(
+            StatusCode::BAD_REQUEST,
+            &json!({ "error": "etcdserver: mvcc: required revision has been compacted", "code": 11 }),
+        );
+        assert!(matches!(error, Error::GRpcStatus(status) if status.code() == Code::OutOfRange));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_watch_stream_error() {
+        let line = br#"{"error":{"grpc_code":11,"http_code":400,"message":"etcdserver: mvcc: required revision has been compacted","http_status":"Bad Request"}}"#;
+        let error = decode_watch_message(line).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::GRpcStatus(status)
+                if status.code() == Code::OutOfRange
+                    && status.message() == "etcdserver: mvcc: required revision has been compacted"
+        ));
+
+        let error = decode_watch_message(br#"{"error":{"message":"closed"}}"#).unwrap_err();
+        assert!(matches!(error, Error::GatewayError(message) if message.contains("closed")));
+    }
+
+    #[test]
+    fn test_txn() -> Result<()> {
+        let txn = Txn::new()
+            .when([Compare::value("foo", CompareOp::Equal, "bar")])
+            .and_then([TxnOp::put("foo", "baz", None)])
+            .or_else([TxnOp::get("foo", None)]);
+        let value = encode_txn(&PbTxnRequest::from(txn));
+        assert_eq!(value["compare"][0]["target"], 3);
+        assert_eq!(value["compare"][0]["value"], "YmFy");
+        assert_eq!(value["success"][0]["request_put"]["value"], "YmF6");
+        assert_eq!(value["failure"][0]["request_range"]["key"], "Zm9v");
+
+        let value = json!({
+            "header": { "revision": "9" },
+            "succeeded": true,
+            "responses": [{ "response_put": { "header": { "revision": "9" } } }],
+        });
+        let resp = decode_txn_response(&Fields::new(&value))?;
+        assert!(resp.succeeded);
+        assert!(matches!(
+            resp.responses[0].response,
+            Some(PbTxnOpResponse::ResponsePut(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_options() {
+        let options = ConnectOptions::new()
+            .with_metadata("tenant", "a")
+            .with_require_leader();
+        let resp = GatewayClient::connect(TEST_ENDPOINTS, Some(options.clone())).await;
+        assert!(matches!(resp, Err(Error::InvalidArgs(_))));
+
+        let resp = Client::connect(TEST_ENDPOINTS, Some(options.with_gateway())).await;
+        assert!(matches!(resp, Err(Error::InvalidArgs(_))));
+    }
+
+    #[tokio::test]
+    async fn test_gateway() -> Result<()> {
+        let client = GatewayClient::connect(TEST_ENDPOINTS, None).await?;
+
+        let resp = client.lease_grant(60, None).await?;
+        let lease = resp.id();
+        client
+            .put("gateway", "123", Some(PutOptions::new().with_lease(lease)))
+            .await?;
+
+        let resp = client.get("gateway", None).await?;
+        let kv = resp.kvs().first().unwrap();
+        assert_eq!(kv.value(), b"123");
+        assert_eq!(kv.lease(), lease);
+
+        let resp = client
+            .lease_time_to_live(lease, Some(LeaseTimeToLiveOptions::new().with_keys()))
+            .await?;
+        assert_eq!(resp.keys(), &[b"gateway".to_vec()]);
+        client.lease_keep_alive(lease).await?;
+
+        let mut stream = client.watch("gateway", None).await?;
+        let resp = stream.message().await?.unwrap();
+        assert!(resp.events().is_empty());
+        client.delete("gateway", None).await?;
+        let resp = stream.message().await?.unwrap();
+        assert_eq!(resp.events().len(), 1);
+
+        let txn = Txn::new()
+            .when([Compare::version("gateway", CompareOp::Equal, 0)])
+            .and_then([TxnOp::put("gateway", "456", None)]);
+        let resp = client.txn(txn).await?;
+        assert!(resp.succeeded());
+        client.delete("gateway", None).await?;
+
+        client.role_add("gateway-role").await?;
+        client
+            .role_grant_permission("gateway-role", Permission::read("gateway"))
+            .await?;
+        let resp = client.role_get("gateway-role").await?;
+        assert_eq!(resp.permissions(), vec![Permission::read("gateway")]);
+        client.role_delete("gateway-role").await?;
+
+        client.lease_revoke(lease).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_through_gateway() -> Result<()> {
+        let options = ConnectOptions::new().with_gateway();
+        let client = Client::connect(TEST_ENDPOINTS, Some(options)).await?;
+
+        let resp = client.lease_grant(60, None).await?;
+        let lease = resp.id();
+        let (mut keeper, mut stream) = client.lease_keep_alive(lease).await?;
+        keeper.keep_alive().await?;
+        let resp = stream.message().await?.unwrap();
+        assert_eq!(resp.id(), lease);
+
+        let (mut watcher, mut stream) = client.watch("client-gateway", None).await?;
+        client
+            .put(
+                "client-gateway",
+                "123",
+                Some(PutOptions::new().with_lease(lease)),
+            )
+            .await?;
+        let resp = stream.message().await?.unwrap();
+        assert_eq!(resp.events().len(), 1);
+        watcher.cancel().await?;
+        let resp = stream.message().await?.unwrap();
+        assert!(resp.canceled());
+        assert!(stream.message().await?.is_none());
+
+        let resp = client.get("client-gateway", None).await?;
+        assert_eq!(resp.kvs().first().unwrap().value(), b"123");
+
+        client.lease_revoke(lease).await?;
+        Ok(())
+    }
+}
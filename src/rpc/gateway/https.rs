@@ -0,0 +1,130 @@
+//! HTTPS connections to the grpc-gateway.
+
+use hyper::client::connect::{Connected, Connection};
+use hyper::client::HttpConnector;
+use hyper::Uri;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use tower_service::Service;
+use webpki::DNSNameRef;
+
+/// Connects to `http` URIs over TCP and to `https` URIs over TLS.
+#[derive(Clone)]
+pub(crate) struct HttpsConnector {
+    http: HttpConnector,
+    tls: TlsConnector,
+    /// name to verify the server certificates against, instead of the host
+    server_name: Option<Arc<str>>,
+}
+
+impl HttpsConnector {
+    /// Creates a connector verifying the servers with `config`.
+    pub(crate) fn new(config: rustls::ClientConfig, server_name: Option<String>) -> Self {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        Self {
+            http,
+            tls: TlsConnector::from(Arc::new(config)),
+            server_name: server_name.map(Into::into),
+        }
+    }
+}
+
+impl Service<Uri> for HttpsConnector {
+    type Response = MaybeHttpsStream;
+    type Error = Box<dyn StdError + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.http.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let is_https = uri.scheme_str() == Some("https");
+        let server_name = match &self.server_name {
+            Some(server_name) => server_name.to_string(),
+            None => uri.host().unwrap_or_default().to_string(),
+        };
+        let connecting = self.http.call(uri);
+        let tls = self.tls.clone();
+        Box::pin(async move {
+            let stream = connecting.await?;
+            if !is_https {
+                return Ok(MaybeHttpsStream::Http(stream));
+            }
+            let domain = DNSNameRef::try_from_ascii_str(&server_name)
+                .map_err(|_| format!("invalid server name {}", server_name))?;
+            let stream = tls.connect(domain, stream).await?;
+            Ok(MaybeHttpsStream::Https(Box::new(stream)))
+        })
+    }
+}
+
+/// A connection to the grpc-gateway, over TLS or not.
+pub(crate) enum MaybeHttpsStream {
+    Http(TcpStream),
+    Https(Box<TlsStream<TcpStream>>),
+}
+
+impl Connection for MaybeHttpsStream {
+    #[inline]
+    fn connected(&self) -> Connected {
+        match self {
+            Self::Http(stream) => stream.connected(),
+            Self::Https(stream) => stream.get_ref().0.connected(),
+        }
+    }
+}
+
+impl AsyncRead for MaybeHttpsStream {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Http(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Https(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeHttpsStream {
+    #[inline]
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Http(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Https(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Http(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Https(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Http(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Https(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
@@ -5,6 +5,7 @@ pub use crate::rpc::pb::etcdserverpb::range_request::{SortOrder, SortTarget};
 
 use crate::auth::AuthService;
 use crate::error::Result;
+use crate::limit::{LimitService, Limits};
 use crate::rpc::pb::etcdserverpb::compare::{CompareTarget, TargetUnion};
 use crate::rpc::pb::etcdserverpb::kv_client::KvClient as PbKvClient;
 use crate::rpc::pb::etcdserverpb::request_op::Request as PbTxnOp;
@@ -17,25 +18,51 @@ use crate::rpc::pb::etcdserverpb::{
     PutResponse as PbPutResponse, RangeRequest as PbRangeRequest, RangeResponse as PbRangeResponse,
     RequestOp as PbTxnRequestOp, TxnRequest as PbTxnRequest, TxnResponse as PbTxnResponse,
 };
-use crate::rpc::{get_prefix, KeyRange, KeyValue, ResponseHeader};
-use http::HeaderValue;
+use crate::rpc::{get_prefix, KeyRange, KeyValue, Metadata, ResponseHeader};
+use http::{HeaderMap, HeaderValue};
 use std::sync::Arc;
 use tonic::transport::Channel;
 use tonic::{IntoRequest, Request};
 
 /// Client for KV operations.
-#[repr(transparent)]
+#[cfg_attr(
+    not(any(feature = "compression", feature = "gateway")),
+    repr(transparent)
+)]
 #[derive(Clone)]
 pub struct KvClient {
-    inner: PbKvClient<AuthService<Channel>>,
+    inner: PbKvClient<LimitService<AuthService<Channel>>>,
+    /// `inner` without compression
+    #[cfg(feature = "compression")]
+    uncompressed: PbKvClient<LimitService<AuthService<Channel>>>,
+    /// sends the requests through the grpc-gateway instead
+    #[cfg(feature = "gateway")]
+    gateway: Option<crate::GatewayClient>,
 }
 
+impl_with_compression!(KvClient);
+impl_with_gateway!(KvClient);
+
 impl KvClient {
     /// Creates a kv client.
     #[inline]
-    pub(crate) fn new(channel: Channel, auth_token: Option<Arc<HeaderValue>>) -> Self {
-        let inner = PbKvClient::new(AuthService::new(channel, auth_token));
-        Self { inner }
+    pub(crate) fn new(
+        channel: Channel,
+        auth_token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+        limits: Limits,
+    ) -> Self {
+        let inner = PbKvClient::new(LimitService::new(
+            AuthService::new(channel, auth_token, metadata),
+            limits,
+        ));
+        Self {
+            #[cfg(feature = "compression")]
+            uncompressed: inner.clone(),
+            inner,
+            #[cfg(feature = "gateway")]
+            gateway: None,
+        }
     }
 
     /// Puts the given key into the key-value store.
@@ -43,14 +70,16 @@ impl KvClient {
     /// and generates one event in the event history.
     #[inline]
     pub async fn put(
-        &mut self,
+        &self,
         key: impl Into<Vec<u8>>,
         value: impl Into<Vec<u8>>,
         options: Option<PutOptions>,
     ) -> Result<PutResponse> {
+        via_gateway!(self, |gateway| gateway.put(key, value, options));
         let resp = self
             .inner
-            .put(options.unwrap_or_default().with_kv(key, value))
+            .clone()
+            .put(options.unwrap_or_default().with_kv(key, value).request()?)
             .await?
             .into_inner();
         Ok(PutResponse::new(resp))
@@ -59,13 +88,15 @@ impl KvClient {
     /// Gets the key or a range of keys from the store.
     #[inline]
     pub async fn get(
-        &mut self,
+        &self,
         key: impl Into<Vec<u8>>,
         options: Option<GetOptions>,
     ) -> Result<GetResponse> {
+        via_gateway!(self, |gateway| gateway.get(key, options));
         let resp = self
             .inner
-            .range(options.unwrap_or_default().with_key(key.into()))
+            .clone()
+            .range(options.unwrap_or_default().with_key(key.into()).request()?)
             .await?
             .into_inner();
         Ok(GetResponse::new(resp))
@@ -74,13 +105,15 @@ impl KvClient {
     /// Deletes the given key or a range of keys from the key-value store.
     #[inline]
     pub async fn delete(
-        &mut self,
+        &self,
         key: impl Into<Vec<u8>>,
         options: Option<DeleteOptions>,
     ) -> Result<DeleteResponse> {
+        via_gateway!(self, |gateway| gateway.delete(key, options));
         let resp = self
             .inner
-            .delete_range(options.unwrap_or_default().with_key(key.into()))
+            .clone()
+            .delete_range(options.unwrap_or_default().with_key(key.into()).request()?)
             .await?
             .into_inner();
         Ok(DeleteResponse::new(resp))
@@ -91,13 +124,20 @@ impl KvClient {
     /// indefinitely.
     #[inline]
     pub async fn compact(
-        &mut self,
+        &self,
         revision: i64,
         options: Option<CompactionOptions>,
     ) -> Result<CompactionResponse> {
+        via_gateway!(self, |gateway| gateway.compact(revision, options));
         let resp = self
             .inner
-            .compact(options.unwrap_or_default().with_revision(revision))
+            .clone()
+            .compact(
+                options
+                    .unwrap_or_default()
+                    .with_revision(revision)
+                    .request()?,
+            )
             .await?
             .into_inner();
         Ok(CompactionResponse::new(resp))
@@ -108,44 +148,50 @@ impl KvClient {
     /// and generates events with the same revision for every completed operation.
     /// It is not allowed to modify the same key several times within one txn.
     #[inline]
-    pub async fn txn(&mut self, txn: Txn) -> Result<TxnResponse> {
-        let resp = self.inner.txn(txn).await?.into_inner();
+    pub async fn txn(&self, txn: Txn) -> Result<TxnResponse> {
+        via_gateway!(self, |gateway| gateway.txn(txn));
+        let resp = self.inner.clone().txn(txn.request()?).await?.into_inner();
         Ok(TxnResponse::new(resp))
     }
 }
 
 /// Options for `Put` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct PutOptions(PbPutRequest);
+pub struct PutOptions {
+    req: PbPutRequest,
+    metadata: Metadata,
+}
 
 impl PutOptions {
     /// Set key-value pair.
     #[inline]
-    fn with_kv(mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> Self {
-        self.0.key = key.into();
-        self.0.value = value.into();
+    pub(crate) fn with_kv(mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> Self {
+        self.req.key = key.into();
+        self.req.value = value.into();
         self
     }
 
     /// Creates a `PutOptions`.
     #[inline]
     pub const fn new() -> Self {
-        Self(PbPutRequest {
-            key: Vec::new(),
-            value: Vec::new(),
-            lease: 0,
-            prev_kv: false,
-            ignore_value: false,
-            ignore_lease: false,
-        })
+        Self {
+            req: PbPutRequest {
+                key: Vec::new(),
+                value: Vec::new(),
+                lease: 0,
+                prev_kv: false,
+                ignore_value: false,
+                ignore_lease: false,
+            },
+            metadata: Metadata::new(),
+        }
     }
 
     /// Lease is the lease ID to associate with the key in the key-value store. A lease
     /// value of 0 indicates no lease.
     #[inline]
     pub const fn with_lease(mut self, lease: i64) -> Self {
-        self.0.lease = lease;
+        self.req.lease = lease;
         self
     }
 
@@ -153,7 +199,7 @@ impl PutOptions {
     /// The previous key-value pair will be returned in the put response.
     #[inline]
     pub const fn with_prev_key(mut self) -> Self {
-        self.0.prev_kv = true;
+        self.req.prev_kv = true;
         self
     }
 
@@ -161,7 +207,7 @@ impl PutOptions {
     /// Returns an error if the key does not exist.
     #[inline]
     pub const fn with_ignore_value(mut self) -> Self {
-        self.0.ignore_value = true;
+        self.req.ignore_value = true;
         self
     }
 
@@ -169,15 +215,32 @@ impl PutOptions {
     /// Returns an error if the key does not exist.
     #[inline]
     pub const fn with_ignore_lease(mut self) -> Self {
-        self.0.ignore_lease = true;
+        self.req.ignore_lease = true;
+        self
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    ///
+    /// Ignored when the options are given to [`TxnOp::put`], since the operations of a
+    /// transaction are sent as one request: set it with [`Txn::with_metadata`] instead.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
         self
     }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbPutRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<PutOptions> for PbPutRequest {
     #[inline]
     fn from(options: PutOptions) -> Self {
-        options.0
+        options.req
     }
 }
 
@@ -197,7 +260,7 @@ pub struct PutResponse(PbPutResponse);
 impl PutResponse {
     /// Create a new `PutResponse` from pb put response.
     #[inline]
-    const fn new(resp: PbPutResponse) -> Self {
+    pub(crate) const fn new(resp: PbPutResponse) -> Self {
         Self(resp)
     }
 
@@ -231,12 +294,13 @@ impl PutResponse {
 pub struct GetOptions {
     req: PbRangeRequest,
     key_range: KeyRange,
+    metadata: Metadata,
 }
 
 impl GetOptions {
     /// Sets key.
     #[inline]
-    fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+    pub(crate) fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
         self.key_range.with_key(key);
         self
     }
@@ -261,6 +325,7 @@ impl GetOptions {
                 max_create_revision: 0,
             },
             key_range: KeyRange::new(),
+            metadata: Metadata::new(),
         }
     }
 
@@ -386,6 +451,23 @@ impl GetOptions {
         self.req.max_create_revision = revision;
         self
     }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    ///
+    /// Ignored when the options are given to [`TxnOp::get`], since the operations of a
+    /// transaction are sent as one request: set it with [`Txn::with_metadata`] instead.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbRangeRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<GetOptions> for PbRangeRequest {
@@ -414,7 +496,7 @@ pub struct GetResponse(PbRangeResponse);
 impl GetResponse {
     /// Create a new `GetResponse` from pb get response.
     #[inline]
-    const fn new(resp: PbRangeResponse) -> Self {
+    pub(crate) const fn new(resp: PbRangeResponse) -> Self {
         Self(resp)
     }
 
@@ -455,12 +537,13 @@ impl GetResponse {
 pub struct DeleteOptions {
     req: PbDeleteRequest,
     key_range: KeyRange,
+    metadata: Metadata,
 }
 
 impl DeleteOptions {
     /// Sets key.
     #[inline]
-    fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+    pub(crate) fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
         self.key_range.with_key(key);
         self
     }
@@ -475,6 +558,7 @@ impl DeleteOptions {
                 prev_kv: false,
             },
             key_range: KeyRange::new(),
+            metadata: Metadata::new(),
         }
     }
 
@@ -513,6 +597,23 @@ impl DeleteOptions {
         self.req.prev_kv = true;
         self
     }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    ///
+    /// Ignored when the options are given to [`TxnOp::delete`], since the operations of a
+    /// transaction are sent as one request: set it with [`Txn::with_metadata`] instead.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbDeleteRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<DeleteOptions> for PbDeleteRequest {
@@ -541,7 +642,7 @@ pub struct DeleteResponse(PbDeleteResponse);
 impl DeleteResponse {
     /// Create a new `DeleteResponse` from pb delete response.
     #[inline]
-    const fn new(resp: PbDeleteResponse) -> Self {
+    pub(crate) const fn new(resp: PbDeleteResponse) -> Self {
         Self(resp)
     }
 
@@ -572,23 +673,28 @@ impl DeleteResponse {
 
 /// Options for `Compact` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct CompactionOptions(PbCompactionRequest);
+pub struct CompactionOptions {
+    req: PbCompactionRequest,
+    metadata: Metadata,
+}
 
 impl CompactionOptions {
     /// Creates a `CompactionOptions`.
     #[inline]
     pub const fn new() -> Self {
-        Self(PbCompactionRequest {
-            revision: 0,
-            physical: false,
-        })
+        Self {
+            req: PbCompactionRequest {
+                revision: 0,
+                physical: false,
+            },
+            metadata: Metadata::new(),
+        }
     }
 
     /// The key-value store revision for the compaction operation.
     #[inline]
-    const fn with_revision(mut self, revision: i64) -> Self {
-        self.0.revision = revision;
+    pub(crate) const fn with_revision(mut self, revision: i64) -> Self {
+        self.req.revision = revision;
         self
     }
 
@@ -597,15 +703,39 @@ impl CompactionOptions {
     /// removed from the backend database.
     #[inline]
     pub const fn with_physical(mut self) -> Self {
-        self.0.physical = true;
+        self.req.physical = true;
+        self
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    ///
+    /// Only sent by [`KvClient::compact`]: the request built by `IntoRequest` carries no
+    /// metadata, since it cannot report invalid metadata.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
         self
     }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbCompactionRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
+}
+
+impl From<CompactionOptions> for PbCompactionRequest {
+    #[inline]
+    fn from(options: CompactionOptions) -> Self {
+        options.req
+    }
 }
 
 impl IntoRequest<PbCompactionRequest> for CompactionOptions {
     #[inline]
     fn into_request(self) -> Request<CompactionRequest> {
-        Request::new(self.0)
+        Request::new(self.into())
     }
 }
 
@@ -618,7 +748,7 @@ pub struct CompactionResponse(PbCompactionResponse);
 impl CompactionResponse {
     /// Create a new `CompactionResponse` from pb compaction response.
     #[inline]
-    const fn new(resp: PbCompactionResponse) -> Self {
+    pub(crate) const fn new(resp: PbCompactionResponse) -> Self {
         Self(resp)
     }
 
@@ -778,6 +908,7 @@ pub struct Txn {
     c_when: bool,
     c_then: bool,
     c_else: bool,
+    metadata: Metadata,
 }
 
 impl Txn {
@@ -793,6 +924,7 @@ impl Txn {
             c_when: false,
             c_then: false,
             c_else: false,
+            metadata: Metadata::new(),
         }
     }
 
@@ -844,6 +976,23 @@ impl Txn {
             .collect();
         self
     }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    ///
+    /// Ignored for a transaction nested with [`TxnOp::txn`], which is sent within the
+    /// request of the outermost transaction.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbTxnRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<Txn> for PbTxnRequest {
@@ -879,7 +1028,7 @@ pub struct TxnResponse(PbTxnResponse);
 impl TxnResponse {
     /// Creates a new `Txn` response.
     #[inline]
-    const fn new(resp: PbTxnResponse) -> Self {
+    pub(crate) const fn new(resp: PbTxnResponse) -> Self {
         Self(resp)
     }
 
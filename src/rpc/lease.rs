@@ -1,7 +1,11 @@
 //! Etcd Lease RPC.
 
 use crate::auth::AuthService;
+use crate::endpoints::EndpointPool;
 use crate::error::Result;
+use crate::limit::Limiter;
+#[cfg(feature = "gateway")]
+use crate::rpc::gateway::GatewayKeepAlive;
 use crate::rpc::pb::etcdserverpb::lease_client::LeaseClient as PbLeaseClient;
 use crate::rpc::pb::etcdserverpb::{
     LeaseGrantRequest as PbLeaseGrantRequest, LeaseGrantResponse as PbLeaseGrantResponse,
@@ -12,31 +16,73 @@ use crate::rpc::pb::etcdserverpb::{
     LeaseTimeToLiveRequest as PbLeaseTimeToLiveRequest,
     LeaseTimeToLiveResponse as PbLeaseTimeToLiveResponse,
 };
-use crate::rpc::ResponseHeader;
+use crate::rpc::{Metadata, ResponseHeader};
 use crate::Error;
-use http::HeaderValue;
+use http::{HeaderMap, HeaderValue};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio::sync::mpsc::{channel, Sender};
+use tokio::sync::OwnedSemaphorePermit;
 use tokio_stream::wrappers::ReceiverStream;
-use tokio_stream::Stream;
+use tokio_stream::{Stream, StreamExt};
 use tonic::transport::Channel;
 use tonic::{IntoRequest, Request, Streaming};
 
 /// Client for lease operations.
-#[repr(transparent)]
 #[derive(Clone)]
 pub struct LeaseClient {
     inner: PbLeaseClient<AuthService<Channel>>,
+    /// `inner` without compression
+    #[cfg(feature = "compression")]
+    uncompressed: PbLeaseClient<AuthService<Channel>>,
+    keep_alive_limiter: Option<Arc<Limiter>>,
+    require_leader: bool,
+    pool: Option<Arc<EndpointPool>>,
+    /// sends the requests through the grpc-gateway instead
+    #[cfg(feature = "gateway")]
+    gateway: Option<crate::GatewayClient>,
 }
 
+impl_with_compression!(LeaseClient);
+impl_with_gateway!(LeaseClient);
+
 impl LeaseClient {
     /// Creates a `LeaseClient`.
     #[inline]
-    pub(crate) fn new(channel: Channel, auth_token: Option<Arc<HeaderValue>>) -> Self {
-        let inner = PbLeaseClient::new(AuthService::new(channel, auth_token));
-        Self { inner }
+    pub(crate) fn new(
+        channel: Channel,
+        auth_token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+        keep_alive_limiter: Option<Arc<Limiter>>,
+    ) -> Self {
+        let inner = PbLeaseClient::new(AuthService::new(channel, auth_token, metadata));
+        Self {
+            #[cfg(feature = "compression")]
+            uncompressed: inner.clone(),
+            inner,
+            keep_alive_limiter,
+            require_leader: false,
+            pool: None,
+            #[cfg(feature = "gateway")]
+            gateway: None,
+        }
+    }
+
+    /// Requires the member to have a leader for keep alive streams.
+    /// A stream then fails with [`Error::NoLeader`] once its member loses the leader,
+    /// instead of keeping the lease alive on a member that cannot renew it.
+    #[inline]
+    pub fn with_require_leader(mut self) -> Self {
+        self.require_leader = true;
+        self
+    }
+
+    /// Evicts the endpoints without a leader from `pool` when a stream loses its leader.
+    #[inline]
+    pub(crate) fn with_endpoint_pool(mut self, pool: Arc<EndpointPool>) -> Self {
+        self.pool = Some(pool);
+        self
     }
 
     /// Creates a lease which expires if the server does not receive a keepAlive
@@ -44,13 +90,15 @@ impl LeaseClient {
     /// deleted if the lease expires. Each expired key generates a delete event in the event history.
     #[inline]
     pub async fn grant(
-        &mut self,
+        &self,
         ttl: i64,
         options: Option<LeaseGrantOptions>,
     ) -> Result<LeaseGrantResponse> {
+        via_gateway!(self, |gateway| gateway.lease_grant(ttl, options));
         let resp = self
             .inner
-            .lease_grant(options.unwrap_or_default().with_ttl(ttl))
+            .clone()
+            .lease_grant(options.unwrap_or_default().with_ttl(ttl).request()?)
             .await?
             .into_inner();
         Ok(LeaseGrantResponse::new(resp))
@@ -58,9 +106,11 @@ impl LeaseClient {
 
     /// Revokes a lease. All keys attached to the lease will expire and be deleted.
     #[inline]
-    pub async fn revoke(&mut self, id: i64) -> Result<LeaseRevokeResponse> {
+    pub async fn revoke(&self, id: i64) -> Result<LeaseRevokeResponse> {
+        via_gateway!(self, |gateway| gateway.lease_revoke(id));
         let resp = self
             .inner
+            .clone()
             .lease_revoke(LeaseRevokeOptions::new().with_id(id))
             .await?
             .into_inner();
@@ -69,19 +119,77 @@ impl LeaseClient {
 
     /// Keeps the lease alive by streaming keep alive requests from the client
     /// to the server and streaming keep alive responses from the server to the client.
-    #[inline]
-    pub async fn keep_alive(&mut self, id: i64) -> Result<(LeaseKeeper, LeaseKeepAliveStream)> {
+    ///
+    /// The keep alive limit of `ConnectOptions` caps the streams open at once,
+    /// and rates the keep alive requests of all streams.
+    ///
+    /// A stream requiring a leader that is opened on a member without one is retried once
+    /// on the other endpoints, after evicting the ones without a leader.
+    #[inline]
+    pub async fn keep_alive(&self, id: i64) -> Result<(LeaseKeeper, LeaseKeepAliveStream)> {
+        let permit = match &self.keep_alive_limiter {
+            Some(limiter) => {
+                let permit = limiter.acquire_in_flight().await?;
+                limiter.acquire_rate().await?;
+                permit
+            }
+            None => None,
+        };
+
+        #[cfg(feature = "gateway")]
+        if let Some(gateway) = &self.gateway {
+            let id = gateway.keep_alive_once(id).await?.id;
+            let (sender, receiver) = channel::<PbLeaseKeepAliveRequest>(100);
+            return Ok((
+                LeaseKeeper::new(id, sender, self.keep_alive_limiter.clone(), permit),
+                LeaseKeepAliveStream::new(
+                    KeepAliveMessages::Gateway(GatewayKeepAlive::new(gateway.clone(), receiver)),
+                    None,
+                ),
+            ));
+        }
+
+        let (sender, stream, id) = match self.open(id).await {
+            Err(Error::NoLeader(_)) if self.evict_leaderless().await => self.open(id).await?,
+            result => result?,
+        };
+
+        Ok((
+            LeaseKeeper::new(id, sender, self.keep_alive_limiter.clone(), permit),
+            LeaseKeepAliveStream::new(KeepAliveMessages::Grpc(stream), self.pool.clone()),
+        ))
+    }
+
+    /// Opens a keep alive stream of the lease `id`.
+    async fn open(
+        &self,
+        id: i64,
+    ) -> Result<(
+        Sender<PbLeaseKeepAliveRequest>,
+        Streaming<PbLeaseKeepAliveResponse>,
+        i64,
+    )> {
         let (sender, receiver) = channel::<PbLeaseKeepAliveRequest>(100);
         sender
             .send(LeaseKeepAliveOptions::new().with_id(id).into())
             .await
             .map_err(|e| Error::LeaseKeepAliveError(e.to_string()))?;
 
-        let receiver = ReceiverStream::new(receiver);
+        let mut metadata = Metadata::new();
+        if self.require_leader {
+            metadata.require_leader();
+        }
+        let receiver = metadata.request(ReceiverStream::new(receiver))?;
 
-        let mut stream = self.inner.lease_keep_alive(receiver).await?.into_inner();
+        let mut stream = self
+            .inner
+            .clone()
+            .lease_keep_alive(receiver)
+            .await
+            .map_err(Error::from_stream_status)?
+            .into_inner();
 
-        let id = match stream.message().await? {
+        let id = match stream.message().await.map_err(Error::from_stream_status)? {
             Some(resp) => resp.id,
             None => {
                 return Err(Error::WatchError(
@@ -89,23 +197,30 @@ impl LeaseClient {
                 ));
             }
         };
+        Ok((sender, stream, id))
+    }
 
-        Ok((
-            LeaseKeeper::new(id, sender),
-            LeaseKeepAliveStream::new(stream),
-        ))
+    /// Evicts the endpoints without a leader, returning whether any was evicted.
+    #[inline]
+    async fn evict_leaderless(&self) -> bool {
+        match &self.pool {
+            Some(pool) => pool.check_leaders().await,
+            None => false,
+        }
     }
 
     /// Retrieves lease information.
     #[inline]
     pub async fn time_to_live(
-        &mut self,
+        &self,
         id: i64,
         options: Option<LeaseTimeToLiveOptions>,
     ) -> Result<LeaseTimeToLiveResponse> {
+        via_gateway!(self, |gateway| gateway.lease_time_to_live(id, options));
         let resp = self
             .inner
-            .lease_time_to_live(options.unwrap_or_default().with_id(id))
+            .clone()
+            .lease_time_to_live(options.unwrap_or_default().with_id(id).request()?)
             .await?
             .into_inner();
         Ok(LeaseTimeToLiveResponse::new(resp))
@@ -113,9 +228,11 @@ impl LeaseClient {
 
     /// Lists all existing leases.
     #[inline]
-    pub async fn leases(&mut self) -> Result<LeaseLeasesResponse> {
+    pub async fn leases(&self) -> Result<LeaseLeasesResponse> {
+        via_gateway!(self, |gateway| gateway.lease_leases());
         let resp = self
             .inner
+            .clone()
             .lease_leases(PbLeaseLeasesRequest {})
             .await?
             .into_inner();
@@ -125,35 +242,54 @@ impl LeaseClient {
 
 /// Options for `Grant` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct LeaseGrantOptions(PbLeaseGrantRequest);
+pub struct LeaseGrantOptions {
+    req: PbLeaseGrantRequest,
+    metadata: Metadata,
+}
 
 impl LeaseGrantOptions {
     /// Set ttl
     #[inline]
-    const fn with_ttl(mut self, ttl: i64) -> Self {
-        self.0.ttl = ttl;
+    pub(crate) const fn with_ttl(mut self, ttl: i64) -> Self {
+        self.req.ttl = ttl;
         self
     }
 
     /// Set id
     #[inline]
     pub const fn with_id(mut self, id: i64) -> Self {
-        self.0.id = id;
+        self.req.id = id;
         self
     }
 
     /// Creates a `LeaseGrantOptions`.
     #[inline]
     pub const fn new() -> Self {
-        Self(PbLeaseGrantRequest { ttl: 0, id: 0 })
+        Self {
+            req: PbLeaseGrantRequest { ttl: 0, id: 0 },
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbLeaseGrantRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
     }
 }
 
 impl From<LeaseGrantOptions> for PbLeaseGrantRequest {
     #[inline]
     fn from(options: LeaseGrantOptions) -> Self {
-        options.0
+        options.req
     }
 }
 
@@ -173,7 +309,7 @@ pub struct LeaseGrantResponse(PbLeaseGrantResponse);
 impl LeaseGrantResponse {
     /// Creates a new `LeaseGrantResponse` from pb lease grant response.
     #[inline]
-    const fn new(resp: PbLeaseGrantResponse) -> Self {
+    pub(crate) const fn new(resp: PbLeaseGrantResponse) -> Self {
         Self(resp)
     }
 
@@ -251,7 +387,7 @@ pub struct LeaseRevokeResponse(PbLeaseRevokeResponse);
 impl LeaseRevokeResponse {
     /// Creates a new `LeaseRevokeResponse` from pb lease revoke response.
     #[inline]
-    const fn new(resp: PbLeaseRevokeResponse) -> Self {
+    pub(crate) const fn new(resp: PbLeaseRevokeResponse) -> Self {
         Self(resp)
     }
 
@@ -311,7 +447,7 @@ pub struct LeaseKeepAliveResponse(PbLeaseKeepAliveResponse);
 impl LeaseKeepAliveResponse {
     /// Creates a new `LeaseKeepAliveResponse` from pb lease KeepAlive response.
     #[inline]
-    const fn new(resp: PbLeaseKeepAliveResponse) -> Self {
+    pub(crate) const fn new(resp: PbLeaseKeepAliveResponse) -> Self {
         Self(resp)
     }
 
@@ -342,35 +478,54 @@ impl LeaseKeepAliveResponse {
 
 /// Options for `TimeToLive` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct LeaseTimeToLiveOptions(PbLeaseTimeToLiveRequest);
+pub struct LeaseTimeToLiveOptions {
+    req: PbLeaseTimeToLiveRequest,
+    metadata: Metadata,
+}
 
 impl LeaseTimeToLiveOptions {
     /// ID is the lease ID for the lease.
     #[inline]
-    const fn with_id(mut self, id: i64) -> Self {
-        self.0.id = id;
+    pub(crate) const fn with_id(mut self, id: i64) -> Self {
+        self.req.id = id;
         self
     }
 
     /// Keys is true to query all the keys attached to this lease.
     #[inline]
     pub const fn with_keys(mut self) -> Self {
-        self.0.keys = true;
+        self.req.keys = true;
         self
     }
 
     /// Creates a `LeaseTimeToLiveOptions`.
     #[inline]
     pub const fn new() -> Self {
-        Self(PbLeaseTimeToLiveRequest { id: 0, keys: false })
+        Self {
+            req: PbLeaseTimeToLiveRequest { id: 0, keys: false },
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbLeaseTimeToLiveRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
     }
 }
 
 impl From<LeaseTimeToLiveOptions> for PbLeaseTimeToLiveRequest {
     #[inline]
     fn from(options: LeaseTimeToLiveOptions) -> Self {
-        options.0
+        options.req
     }
 }
 
@@ -390,7 +545,7 @@ pub struct LeaseTimeToLiveResponse(PbLeaseTimeToLiveResponse);
 impl LeaseTimeToLiveResponse {
     /// Creates a new `LeaseTimeToLiveResponse` from pb lease TimeToLive response.
     #[inline]
-    const fn new(resp: PbLeaseTimeToLiveResponse) -> Self {
+    pub(crate) const fn new(resp: PbLeaseTimeToLiveResponse) -> Self {
         Self(resp)
     }
 
@@ -440,7 +595,7 @@ pub struct LeaseLeasesResponse(PbLeaseLeasesResponse);
 impl LeaseLeasesResponse {
     /// Creates a new `LeaseLeasesResponse` from pb lease Leases response.
     #[inline]
-    const fn new(resp: PbLeaseLeasesResponse) -> Self {
+    pub(crate) const fn new(resp: PbLeaseLeasesResponse) -> Self {
         Self(resp)
     }
 
@@ -490,13 +645,26 @@ impl From<&PbLeaseStatus> for &LeaseStatus {
 pub struct LeaseKeeper {
     id: i64,
     sender: Sender<PbLeaseKeepAliveRequest>,
+    limiter: Option<Arc<Limiter>>,
+    /// counts the stream open until dropped
+    _permit: Option<OwnedSemaphorePermit>,
 }
 
 impl LeaseKeeper {
     /// Creates a new `LeaseKeeper`.
     #[inline]
-    const fn new(id: i64, sender: Sender<PbLeaseKeepAliveRequest>) -> Self {
-        Self { id, sender }
+    const fn new(
+        id: i64,
+        sender: Sender<PbLeaseKeepAliveRequest>,
+        limiter: Option<Arc<Limiter>>,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> Self {
+        Self {
+            id,
+            sender,
+            limiter,
+            _permit: permit,
+        }
     }
 
     /// The lease id which user want to keep alive.
@@ -508,6 +676,9 @@ impl LeaseKeeper {
     /// Sends a keep alive request and receive response
     #[inline]
     pub async fn keep_alive(&mut self) -> Result<()> {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire_rate().await?;
+        }
         self.sender
             .send(LeaseKeepAliveOptions::new().with_id(self.id).into())
             .await
@@ -519,23 +690,31 @@ impl LeaseKeeper {
 #[cfg_attr(feature = "pub-response-field", visible::StructFields(pub))]
 #[derive(Debug)]
 pub struct LeaseKeepAliveStream {
-    stream: Streaming<PbLeaseKeepAliveResponse>,
+    stream: KeepAliveMessages,
+    /// endpoints checked for a leader when the stream loses it
+    pool: Option<Arc<EndpointPool>>,
 }
 
 impl LeaseKeepAliveStream {
     /// Creates a new `LeaseKeepAliveStream`.
     #[inline]
-    const fn new(stream: Streaming<PbLeaseKeepAliveResponse>) -> Self {
-        Self { stream }
+    const fn new(stream: KeepAliveMessages, pool: Option<Arc<EndpointPool>>) -> Self {
+        Self { stream, pool }
     }
 
     /// Fetches the next message from this stream.
     #[inline]
     pub async fn message(&mut self) -> Result<Option<LeaseKeepAliveResponse>> {
-        match self.stream.message().await? {
-            Some(resp) => Ok(Some(LeaseKeepAliveResponse::new(resp))),
-            None => Ok(None),
+        self.next().await.transpose()
+    }
+
+    /// Checks the leaders once an error of the stream shows one is lost.
+    #[inline]
+    fn stream_error(&self, e: Error) -> Error {
+        if let (Error::NoLeader(_), Some(pool)) = (&e, &self.pool) {
+            pool.spawn_leader_check();
         }
+        e
     }
 }
 
@@ -544,12 +723,38 @@ impl Stream for LeaseKeepAliveStream {
 
     #[inline]
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.get_mut().stream)
-            .poll_next(cx)
-            .map(|t| match t {
-                Some(Ok(resp)) => Some(Ok(LeaseKeepAliveResponse::new(resp))),
-                Some(Err(e)) => Some(Err(From::from(e))),
-                None => None,
-            })
+        let this = self.get_mut();
+        this.stream.poll_message(cx).map(|t| match t {
+            Some(Ok(resp)) => Some(Ok(LeaseKeepAliveResponse::new(resp))),
+            Some(Err(e)) => Some(Err(this.stream_error(e))),
+            None => None,
+        })
+    }
+}
+
+/// The messages of a [`LeaseKeepAliveStream`], received over gRPC or the grpc-gateway.
+#[derive(Debug)]
+pub enum KeepAliveMessages {
+    /// A bidirectional gRPC stream.
+    Grpc(Streaming<PbLeaseKeepAliveResponse>),
+    /// Keep alives over the grpc-gateway.
+    #[cfg(feature = "gateway")]
+    Gateway(GatewayKeepAlive),
+}
+
+impl KeepAliveMessages {
+    /// Polls the next message of the stream.
+    #[inline]
+    fn poll_message(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<PbLeaseKeepAliveResponse>>> {
+        match self {
+            Self::Grpc(stream) => Pin::new(stream)
+                .poll_next(cx)
+                .map(|resp| resp.map(|resp| resp.map_err(Error::from_stream_status))),
+            #[cfg(feature = "gateway")]
+            Self::Gateway(keep_alive) => keep_alive.poll_message(cx),
+        }
     }
 }
@@ -3,8 +3,8 @@
 use super::pb::v3lockpb;
 use crate::auth::AuthService;
 use crate::error::Result;
-use crate::rpc::ResponseHeader;
-use http::HeaderValue;
+use crate::rpc::{Metadata, ResponseHeader};
+use http::{HeaderMap, HeaderValue};
 use std::sync::Arc;
 use tonic::transport::Channel;
 use tonic::{IntoRequest, Request};
@@ -15,18 +15,31 @@ use v3lockpb::{
 };
 
 /// Client for Lock operations.
-#[repr(transparent)]
+#[cfg_attr(not(feature = "compression"), repr(transparent))]
 #[derive(Clone)]
 pub struct LockClient {
     inner: PbLockClient<AuthService<Channel>>,
+    /// `inner` without compression
+    #[cfg(feature = "compression")]
+    uncompressed: PbLockClient<AuthService<Channel>>,
 }
 
+impl_with_compression!(LockClient);
+
 impl LockClient {
     /// Creates a lock client.
     #[inline]
-    pub(crate) fn new(channel: Channel, auth_token: Option<Arc<HeaderValue>>) -> Self {
-        let inner = PbLockClient::new(AuthService::new(channel, auth_token));
-        Self { inner }
+    pub(crate) fn new(
+        channel: Channel,
+        auth_token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+    ) -> Self {
+        let inner = PbLockClient::new(AuthService::new(channel, auth_token, metadata));
+        Self {
+            #[cfg(feature = "compression")]
+            uncompressed: inner.clone(),
+            inner,
+        }
     }
 
     /// Acquires a distributed shared lock on a given named lock.
@@ -37,13 +50,14 @@ impl LockClient {
     /// lease associate with the owner expires.
     #[inline]
     pub async fn lock(
-        &mut self,
+        &self,
         name: impl Into<Vec<u8>>,
         options: Option<LockOptions>,
     ) -> Result<LockResponse> {
         let resp = self
             .inner
-            .lock(options.unwrap_or_default().with_name(name))
+            .clone()
+            .lock(options.unwrap_or_default().with_name(name).request()?)
             .await?
             .into_inner();
         Ok(LockResponse::new(resp))
@@ -53,9 +67,10 @@ impl LockClient {
     /// next Lock caller waiting for the lock will then be woken up and given
     /// ownership of the lock.
     #[inline]
-    pub async fn unlock(&mut self, key: impl Into<Vec<u8>>) -> Result<UnlockResponse> {
+    pub async fn unlock(&self, key: impl Into<Vec<u8>>) -> Result<UnlockResponse> {
         let resp = self
             .inner
+            .clone()
             .unlock(UnlockOptions::new().with_key(key))
             .await?
             .into_inner();
@@ -65,24 +80,29 @@ impl LockClient {
 
 /// Options for `Lock` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct LockOptions(PbLockRequest);
+pub struct LockOptions {
+    req: PbLockRequest,
+    metadata: Metadata,
+}
 
 impl LockOptions {
     /// name is the identifier for the distributed shared lock to be acquired.
     #[inline]
     fn with_name(mut self, name: impl Into<Vec<u8>>) -> Self {
-        self.0.name = name.into();
+        self.req.name = name.into();
         self
     }
 
     /// Creates a `LockOptions`.
     #[inline]
     pub const fn new() -> Self {
-        Self(PbLockRequest {
-            name: Vec::new(),
-            lease: 0,
-        })
+        Self {
+            req: PbLockRequest {
+                name: Vec::new(),
+                lease: 0,
+            },
+            metadata: Metadata::new(),
+        }
     }
 
     /// `lease` is the ID of the lease that will be attached to ownership of the
@@ -92,15 +112,29 @@ impl LockOptions {
     /// no-op.
     #[inline]
     pub const fn with_lease(mut self, lease: i64) -> Self {
-        self.0.lease = lease;
+        self.req.lease = lease;
+        self
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
         self
     }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbLockRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
+    }
 }
 
 impl From<LockOptions> for PbLockRequest {
     #[inline]
     fn from(options: LockOptions) -> Self {
-        options.0
+        options.req
     }
 }
 
@@ -5,7 +5,7 @@ pub use crate::rpc::pb::etcdserverpb::AlarmType;
 
 use super::pb::etcdserverpb;
 use crate::auth::AuthService;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::rpc::pb::etcdserverpb::{
     AlarmRequest as PbAlarmRequest, AlarmResponse as PbAlarmResponse,
     DefragmentRequest as PbDefragmentRequest, DefragmentResponse as PbDefragmentResponse,
@@ -15,36 +15,48 @@ use crate::rpc::pb::etcdserverpb::{
     SnapshotRequest as PbSnapshotRequest, SnapshotResponse as PbSnapshotResponse,
     StatusRequest as PbStatusRequest, StatusResponse as PbStatusResponse,
 };
-use crate::rpc::ResponseHeader;
+use crate::rpc::{Metadata, ResponseHeader};
 use etcdserverpb::maintenance_client::MaintenanceClient as PbMaintenanceClient;
 use etcdserverpb::AlarmMember as PbAlarmMember;
-use http::HeaderValue;
+use http::{HeaderMap, HeaderValue};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
 use tonic::codec::Streaming as PbStreaming;
 use tonic::transport::Channel;
 use tonic::{IntoRequest, Request};
 
 /// Client for maintenance operations.
-#[repr(transparent)]
+#[cfg_attr(not(feature = "compression"), repr(transparent))]
 #[derive(Clone)]
 pub struct MaintenanceClient {
     inner: PbMaintenanceClient<AuthService<Channel>>,
+    /// `inner` without compression
+    #[cfg(feature = "compression")]
+    uncompressed: PbMaintenanceClient<AuthService<Channel>>,
 }
 
 /// Options for `alarm` operation.
 #[derive(Debug, Default, Clone)]
-#[repr(transparent)]
-pub struct AlarmOptions(PbAlarmRequest);
+pub struct AlarmOptions {
+    req: PbAlarmRequest,
+    metadata: Metadata,
+}
 
 impl AlarmOptions {
     /// Creates a new `AlarmOptions`.
     #[inline]
     pub const fn new() -> Self {
-        AlarmOptions(PbAlarmRequest {
-            action: AlarmAction::Get as i32,
-            member_id: 0,
-            alarm: AlarmType::None as i32,
-        })
+        Self {
+            req: PbAlarmRequest {
+                action: AlarmAction::Get as i32,
+                member_id: 0,
+                alarm: AlarmType::None as i32,
+            },
+            metadata: Metadata::new(),
+        }
     }
 
     /// Sets alarm action and alarm type.
@@ -54,22 +66,36 @@ impl AlarmOptions {
         alarm_action: AlarmAction,
         alarm_type: AlarmType,
     ) -> Self {
-        self.0.action = alarm_action as i32;
-        self.0.alarm = alarm_type as i32;
+        self.req.action = alarm_action as i32;
+        self.req.alarm = alarm_type as i32;
         self
     }
 
     /// Sets alarm member.
     #[inline]
     pub fn with_member(&mut self, member: u64) {
-        self.0.member_id = member;
+        self.req.member_id = member;
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for this request.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// Creates the request carrying the metadata.
+    #[inline]
+    pub(crate) fn request(mut self) -> Result<Request<PbAlarmRequest>> {
+        let metadata = std::mem::take(&mut self.metadata);
+        metadata.request(self.into())
     }
 }
 
 impl From<AlarmOptions> for PbAlarmRequest {
     #[inline]
     fn from(alarm: AlarmOptions) -> Self {
-        alarm.0
+        alarm.req
     }
 }
 
@@ -214,7 +240,7 @@ pub struct AlarmResponse(PbAlarmResponse);
 
 /// Alarm member of respond.
 #[cfg_attr(feature = "pub-response-field", visible::StructFields(pub))]
-#[derive(Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AlarmMember {
     /// memberID is the ID of the member associated with the raised alarm.
     member_id: u64,
@@ -492,6 +518,105 @@ impl SnapshotStreaming {
             None => Ok(None),
         }
     }
+
+    /// Saves the snapshot to the file at `path`, returning the number of bytes written.
+    ///
+    /// The snapshot is streamed to `<path>.part` first, and renamed to `path` only after
+    /// the SHA-256 digest appended by etcd is verified. `progress` is called after each
+    /// chunk with the number of bytes received so far and the remaining bytes.
+    pub async fn save<F>(mut self, path: impl AsRef<Path>, mut progress: F) -> Result<u64>
+    where
+        F: FnMut(u64, u64),
+    {
+        let path = path.as_ref();
+        let mut part_path = path.as_os_str().to_owned();
+        part_path.push(".part");
+        let part_path = PathBuf::from(part_path);
+
+        let result = async {
+            let mut file = File::create(&part_path).await?;
+            let mut verifier = SnapshotVerifier::new();
+            let mut received = 0;
+
+            while let Some(resp) = self.message().await? {
+                file.write_all(resp.blob()).await?;
+                verifier.update(resp.blob());
+                received += resp.blob().len() as u64;
+                progress(received, resp.remaining_bytes());
+            }
+
+            verifier.verify()?;
+            file.sync_all().await?;
+            Ok(received)
+        }
+        .await;
+
+        match result {
+            Ok(received) => {
+                fs::rename(&part_path, path).await?;
+                Ok(received)
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&part_path).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Verifies the SHA-256 digest etcd appends to the snapshot db.
+struct SnapshotVerifier {
+    hasher: Sha256,
+    /// The trailing bytes that may belong to the digest.
+    tail: Vec<u8>,
+}
+
+impl SnapshotVerifier {
+    /// Size of the SHA-256 digest.
+    const DIGEST_SIZE: usize = 32;
+
+    /// Creates a new `SnapshotVerifier`.
+    #[inline]
+    fn new() -> Self {
+        Self {
+            hasher: Sha256::new(),
+            tail: Vec::with_capacity(Self::DIGEST_SIZE * 2),
+        }
+    }
+
+    /// Feeds the next chunk of the snapshot.
+    fn update(&mut self, blob: &[u8]) {
+        if blob.len() >= Self::DIGEST_SIZE {
+            // the previous tail and all but the end of the chunk are db bytes
+            let n = blob.len() - Self::DIGEST_SIZE;
+            self.hasher.update(&self.tail);
+            self.hasher.update(&blob[..n]);
+            self.tail.clear();
+            self.tail.extend_from_slice(&blob[n..]);
+            return;
+        }
+
+        self.tail.extend_from_slice(blob);
+        if self.tail.len() > Self::DIGEST_SIZE {
+            let n = self.tail.len() - Self::DIGEST_SIZE;
+            self.hasher.update(&self.tail[..n]);
+            self.tail.drain(..n);
+        }
+    }
+
+    /// Checks the trailing digest against the hash of the received db.
+    fn verify(self) -> Result<()> {
+        if self.tail.len() != Self::DIGEST_SIZE {
+            return Err(Error::SnapshotError(
+                "snapshot is too short to contain a digest".to_string(),
+            ));
+        }
+
+        if self.hasher.finalize()[..] != self.tail[..] {
+            return Err(Error::SnapshotError("snapshot digest mismatch".to_string()));
+        }
+        Ok(())
+    }
 }
 
 /// Options for `MoveLeader` operation.
@@ -553,28 +678,40 @@ impl MoveLeaderResponse {
     }
 }
 
+impl_with_compression!(MaintenanceClient);
+
 impl MaintenanceClient {
     /// Creates a maintenance client.
     #[inline]
-    pub(crate) fn new(channel: Channel, auth_token: Option<Arc<HeaderValue>>) -> Self {
-        let inner = PbMaintenanceClient::new(AuthService::new(channel, auth_token));
-        Self { inner }
+    pub(crate) fn new(
+        channel: Channel,
+        auth_token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+    ) -> Self {
+        let inner = PbMaintenanceClient::new(AuthService::new(channel, auth_token, metadata));
+        Self {
+            #[cfg(feature = "compression")]
+            uncompressed: inner.clone(),
+            inner,
+        }
     }
 
     /// Get or active or inactive alarm.
     #[inline]
     pub async fn alarm(
-        &mut self,
+        &self,
         alarm_action: AlarmAction,
         alarm_type: AlarmType,
         options: Option<AlarmOptions>,
     ) -> Result<AlarmResponse> {
         let resp = self
             .inner
+            .clone()
             .alarm(
                 options
                     .unwrap_or_default()
-                    .with_action_and_type(alarm_action, alarm_type),
+                    .with_action_and_type(alarm_action, alarm_type)
+                    .request()?,
             )
             .await?
             .into_inner();
@@ -583,16 +720,22 @@ impl MaintenanceClient {
 
     /// Get status of a member.
     #[inline]
-    pub async fn status(&mut self) -> Result<StatusResponse> {
-        let resp = self.inner.status(StatusOptions::new()).await?.into_inner();
+    pub async fn status(&self) -> Result<StatusResponse> {
+        let resp = self
+            .inner
+            .clone()
+            .status(StatusOptions::new())
+            .await?
+            .into_inner();
         Ok(StatusResponse::new(resp))
     }
 
     /// Defragment a member's backend database to recover storage space.
     #[inline]
-    pub async fn defragment(&mut self) -> Result<DefragmentResponse> {
+    pub async fn defragment(&self) -> Result<DefragmentResponse> {
         let resp = self
             .inner
+            .clone()
             .defragment(DefragmentOptions::new())
             .await?
             .into_inner();
@@ -603,17 +746,23 @@ impl MaintenanceClient {
     /// including key, lease, and other buckets in storage.
     /// This is designed for testing ONLY!
     #[inline]
-    pub async fn hash(&mut self) -> Result<HashResponse> {
-        let resp = self.inner.hash(HashOptions::new()).await?.into_inner();
+    pub async fn hash(&self) -> Result<HashResponse> {
+        let resp = self
+            .inner
+            .clone()
+            .hash(HashOptions::new())
+            .await?
+            .into_inner();
         Ok(HashResponse::new(resp))
     }
 
     /// Computes the hash of all MVCC keys up to a given revision.
     /// It only iterates \"key\" bucket in backend storage.
     #[inline]
-    pub async fn hash_kv(&mut self, revision: i64) -> Result<HashKvResponse> {
+    pub async fn hash_kv(&self, revision: i64) -> Result<HashKvResponse> {
         let resp = self
             .inner
+            .clone()
             .hash_kv(HashKvOptions::new(revision))
             .await?
             .into_inner();
@@ -622,23 +771,81 @@ impl MaintenanceClient {
 
     /// Gets a snapshot of the entire backend from a member over a stream to a client.
     #[inline]
-    pub async fn snapshot(&mut self) -> Result<SnapshotStreaming> {
+    pub async fn snapshot(&self) -> Result<SnapshotStreaming> {
         let resp = self
             .inner
+            .clone()
             .snapshot(SnapshotOptions::new())
             .await?
             .into_inner();
         Ok(SnapshotStreaming(resp))
     }
 
+    /// Saves a snapshot of the entire backend to the file at `path`,
+    /// verifying its integrity. See [`SnapshotStreaming::save`].
+    #[inline]
+    pub async fn save_snapshot<F>(&self, path: impl AsRef<Path>, progress: F) -> Result<u64>
+    where
+        F: FnMut(u64, u64),
+    {
+        self.snapshot().await?.save(path, progress).await
+    }
+
     /// Moves the current leader node to target node.
     #[inline]
-    pub async fn move_leader(&mut self, target_id: u64) -> Result<MoveLeaderResponse> {
+    pub async fn move_leader(&self, target_id: u64) -> Result<MoveLeaderResponse> {
         let resp = self
             .inner
+            .clone()
             .move_leader(MoveLeaderOptions::new().with_target_id(target_id))
             .await?
             .into_inner();
         Ok(MoveLeaderResponse::new(resp))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends the SHA-256 digest of `db` like etcd does.
+    fn with_digest(db: &[u8]) -> Vec<u8> {
+        let mut data = db.to_vec();
+        data.extend_from_slice(&Sha256::digest(db));
+        data
+    }
+
+    #[test]
+    fn test_snapshot_verifier() {
+        let data = with_digest(b"etcd snapshot db");
+
+        // digest split across chunks of any size
+        for chunk_size in [1, 7, 32, data.len()].iter() {
+            let mut verifier = SnapshotVerifier::new();
+            for chunk in data.chunks(*chunk_size) {
+                verifier.update(chunk);
+            }
+            assert!(verifier.verify().is_ok());
+        }
+
+        // chunks both shorter and longer than the digest
+        let data = with_digest(&[7; 100]);
+        let mut verifier = SnapshotVerifier::new();
+        for chunk in [&data[..40], &data[40..45], &data[45..90], &data[90..]].iter() {
+            verifier.update(chunk);
+        }
+        assert!(verifier.verify().is_ok());
+
+        // corrupted db
+        let mut corrupted = data.clone();
+        corrupted[0] ^= 0xff;
+        let mut verifier = SnapshotVerifier::new();
+        verifier.update(&corrupted);
+        assert!(verifier.verify().is_err());
+
+        // truncated
+        let mut verifier = SnapshotVerifier::new();
+        verifier.update(&data[..16]);
+        assert!(verifier.verify().is_err());
+    }
+}
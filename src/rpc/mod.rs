@@ -6,18 +6,73 @@ pub(crate) mod pb;
 #[cfg(not(feature = "pub-response-field"))]
 mod pb;
 
+/// Implements `with_compression` for a service client wrapping the generated client in `inner`,
+/// and keeping it without compression in `uncompressed`.
+macro_rules! impl_with_compression {
+    ($client:ident) => {
+        #[cfg(feature = "compression")]
+        impl $client {
+            /// Sets the `gzip` compression of the requests of this client, replacing the
+            /// compression configured in `ConnectOptions` or by a previous call.
+            #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+            #[inline]
+            pub fn with_compression(mut self, compression: crate::CompressionOptions) -> Self {
+                self.inner = self.uncompressed.clone();
+                if compression.send_gzip() {
+                    self.inner = self.inner.send_gzip();
+                }
+                if compression.accept_gzip() {
+                    self.inner = self.inner.accept_gzip();
+                }
+                self
+            }
+        }
+    };
+}
+
+/// Implements `with_gateway` for a service client sending its requests through the
+/// grpc-gateway client in `gateway` when set.
+macro_rules! impl_with_gateway {
+    ($client:ident) => {
+        #[cfg(feature = "gateway")]
+        impl $client {
+            /// Sends the requests of this client through the grpc-gateway.
+            #[inline]
+            pub(crate) fn with_gateway(mut self, gateway: crate::GatewayClient) -> Self {
+                self.gateway = Some(gateway);
+                self
+            }
+        }
+    };
+}
+
+/// Returns the response of the request sent through the grpc-gateway of the client, if set.
+macro_rules! via_gateway {
+    ($client:expr, |$gateway:ident| $call:expr) => {
+        #[cfg(feature = "gateway")]
+        if let Some($gateway) = &$client.gateway {
+            return $call.await;
+        }
+    };
+}
+
 pub mod auth;
 pub mod cluster;
 pub mod election;
+#[cfg(feature = "gateway")]
+pub mod gateway;
 pub mod kv;
 pub mod lease;
 pub mod lock;
 pub mod maintenance;
 pub mod watch;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 use pb::etcdserverpb::ResponseHeader as PbResponseHeader;
 use pb::mvccpb::KeyValue as PbKeyValue;
+use tonic::metadata::MetadataMap;
+use tonic::Request;
 
 /// General `etcd` response header.
 #[cfg_attr(feature = "pub-response-field", visible::StructFields(pub))]
@@ -263,6 +318,62 @@ impl KeyRange {
     }
 }
 
+/// Metadata key making etcd cancel a stream when its member loses the leader.
+const REQUIRE_LEADER_KEY: &str = "hasleader";
+
+/// Custom gRPC metadata builder, validated when the request is sent.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Metadata(Vec<(String, String)>);
+
+impl Metadata {
+    #[inline]
+    pub const fn new() -> Self {
+        Metadata(Vec::new())
+    }
+
+    /// Sets the value of `key`, replacing the previous one.
+    #[inline]
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        self.0.retain(|(k, _)| !k.eq_ignore_ascii_case(&key));
+        self.0.push((key, value.into()));
+    }
+
+    /// Requires the member to have a leader, like `WithRequireLeader` of the Go client.
+    #[inline]
+    pub fn require_leader(&mut self) {
+        self.insert(REQUIRE_LEADER_KEY, "true");
+    }
+
+    /// Whether no metadata is set.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Parses the metadata into headers.
+    pub fn headers(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::with_capacity(self.0.len());
+        for (key, value) in &self.0 {
+            let name = HeaderName::from_bytes(key.as_bytes()).map_err(|e| {
+                Error::InvalidArgs(format!("invalid metadata key {:?}: {}", key, e))
+            })?;
+            headers.insert(name, HeaderValue::from_str(value)?);
+        }
+        Ok(headers)
+    }
+
+    /// Creates a request of `message` carrying the metadata.
+    #[inline]
+    pub fn request<T>(&self, message: T) -> Result<Request<T>> {
+        let mut request = Request::new(message);
+        if !self.is_empty() {
+            *request.metadata_mut() = MetadataMap::from_headers(self.headers()?);
+        }
+        Ok(request)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,4 +384,19 @@ mod tests {
         assert_eq!(get_prefix(b"\xFF").as_slice(), b"\0");
         assert_eq!(get_prefix(b"foo\xFF").as_slice(), b"fop");
     }
+
+    #[test]
+    fn test_metadata() -> Result<()> {
+        let mut metadata = Metadata::new();
+        metadata.insert("x-tenant-id", "a");
+        metadata.insert("X-Tenant-Id", "b");
+        metadata.insert("x-request-id", "42");
+        let request = metadata.request(())?;
+        assert_eq!(request.metadata().len(), 2);
+        assert_eq!(request.metadata().get("x-tenant-id").unwrap(), "b");
+
+        metadata.insert("bad key", "c");
+        assert!(metadata.request(()).is_err());
+        Ok(())
+    }
 }
@@ -3,7 +3,10 @@
 pub use crate::rpc::pb::mvccpb::event::EventType;
 
 use crate::auth::AuthService;
+use crate::endpoints::EndpointPool;
 use crate::error::{Error, Result};
+#[cfg(feature = "gateway")]
+use crate::rpc::gateway::GatewayWatch;
 use crate::rpc::pb::etcdserverpb::watch_client::WatchClient as PbWatchClient;
 use crate::rpc::pb::etcdserverpb::watch_request::RequestUnion as WatchRequestUnion;
 use crate::rpc::pb::etcdserverpb::{
@@ -11,29 +14,65 @@ use crate::rpc::pb::etcdserverpb::{
     WatchResponse as PbWatchResponse,
 };
 use crate::rpc::pb::mvccpb::Event as PbEvent;
-use crate::rpc::{KeyRange, KeyValue, ResponseHeader};
-use http::HeaderValue;
+use crate::rpc::{KeyRange, KeyValue, Metadata, ResponseHeader};
+use http::{HeaderMap, HeaderValue};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio::sync::mpsc::{channel, Sender};
-use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tonic::transport::Channel;
 use tonic::Streaming;
 
 /// Client for watch operations.
-#[repr(transparent)]
 #[derive(Clone)]
 pub struct WatchClient {
     inner: PbWatchClient<AuthService<Channel>>,
+    /// `inner` without compression
+    #[cfg(feature = "compression")]
+    uncompressed: PbWatchClient<AuthService<Channel>>,
+    require_leader: bool,
+    pool: Option<Arc<EndpointPool>>,
+    /// sends the requests through the grpc-gateway instead
+    #[cfg(feature = "gateway")]
+    gateway: Option<crate::GatewayClient>,
 }
 
+impl_with_compression!(WatchClient);
+impl_with_gateway!(WatchClient);
+
 impl WatchClient {
     /// Creates a watch client.
     #[inline]
-    pub(crate) fn new(channel: Channel, auth_token: Option<Arc<HeaderValue>>) -> Self {
-        let inner = PbWatchClient::new(AuthService::new(channel, auth_token));
-        Self { inner }
+    pub(crate) fn new(
+        channel: Channel,
+        auth_token: Option<Arc<HeaderValue>>,
+        metadata: Option<Arc<HeaderMap>>,
+    ) -> Self {
+        let inner = PbWatchClient::new(AuthService::new(channel, auth_token, metadata));
+        Self {
+            #[cfg(feature = "compression")]
+            uncompressed: inner.clone(),
+            inner,
+            require_leader: false,
+            pool: None,
+            #[cfg(feature = "gateway")]
+            gateway: None,
+        }
+    }
+
+    /// Requires a leader for all watches, like [`WatchOptions::with_require_leader`].
+    #[inline]
+    pub fn with_require_leader(mut self) -> Self {
+        self.require_leader = true;
+        self
+    }
+
+    /// Evicts the endpoints without a leader from `pool` when a watch loses its leader.
+    #[inline]
+    pub(crate) fn with_endpoint_pool(mut self, pool: Arc<EndpointPool>) -> Self {
+        self.pool = Some(pool);
+        self
     }
 
     /// Watches for events happening or that have happened. Both input and output
@@ -41,22 +80,68 @@ impl WatchClient {
     /// stream sends events. One watch RPC can watch on multiple key ranges, streaming events
     /// for several watches at once. The entire event history can be watched starting from the
     /// last compaction revision.
+    ///
+    /// A watch requiring a leader that is created on a member without one is retried once
+    /// on the other endpoints, after evicting the ones without a leader.
     pub async fn watch(
-        &mut self,
+        &self,
         key: impl Into<Vec<u8>>,
         options: Option<WatchOptions>,
     ) -> Result<(Watcher, WatchStream)> {
+        let mut options = options.unwrap_or_default();
+        let reassemble = !options.raw_fragments;
+        if self.require_leader {
+            options.metadata.require_leader();
+        }
+        let metadata = std::mem::take(&mut options.metadata);
+        let request: WatchRequest = options.with_key(key).into();
+
+        #[cfg(feature = "gateway")]
+        if let Some(gateway) = &self.gateway {
+            let (sender, receiver) = channel::<WatchRequest>(100);
+            let (watch, watch_id) = GatewayWatch::open(gateway, &request, receiver).await?;
+            return Ok((
+                Watcher::new(watch_id, sender),
+                WatchStream::new(WatchMessages::Gateway(watch), reassemble, None),
+            ));
+        }
+
+        let (sender, stream, watch_id) = match self.open(request.clone(), &metadata).await {
+            Err(Error::NoLeader(_)) if self.evict_leaderless().await => {
+                self.open(request, &metadata).await?
+            }
+            result => result?,
+        };
+
+        Ok((
+            Watcher::new(watch_id, sender),
+            WatchStream::new(WatchMessages::Grpc(stream), reassemble, self.pool.clone()),
+        ))
+    }
+
+    /// Opens a watch stream creating the watch of `request`.
+    async fn open(
+        &self,
+        request: WatchRequest,
+        metadata: &Metadata,
+    ) -> Result<(Sender<WatchRequest>, Streaming<PbWatchResponse>, i64)> {
         let (sender, receiver) = channel::<WatchRequest>(100);
         sender
-            .send(options.unwrap_or_default().with_key(key).into())
+            .send(request)
             .await
             .map_err(|e| Error::WatchError(e.to_string()))?;
 
-        let receiver = ReceiverStream::new(receiver);
+        let receiver = metadata.request(ReceiverStream::new(receiver))?;
 
-        let mut stream = self.inner.watch(receiver).await?.into_inner();
+        let mut stream = self
+            .inner
+            .clone()
+            .watch(receiver)
+            .await
+            .map_err(Error::from_stream_status)?
+            .into_inner();
 
-        let watch_id = match stream.message().await? {
+        let watch_id = match stream.message().await.map_err(Error::from_stream_status)? {
             Some(resp) => {
                 assert!(resp.created, "not a create watch response");
                 resp.watch_id
@@ -65,8 +150,16 @@ impl WatchClient {
                 return Err(Error::WatchError("failed to create watch".to_string()));
             }
         };
+        Ok((sender, stream, watch_id))
+    }
 
-        Ok((Watcher::new(watch_id, sender), WatchStream::new(stream)))
+    /// Evicts the endpoints without a leader, returning whether any was evicted.
+    #[inline]
+    async fn evict_leaderless(&self) -> bool {
+        match &self.pool {
+            Some(pool) => pool.check_leaders().await,
+            None => false,
+        }
     }
 }
 
@@ -75,12 +168,14 @@ impl WatchClient {
 pub struct WatchOptions {
     req: WatchCreateRequest,
     key_range: KeyRange,
+    raw_fragments: bool,
+    metadata: Metadata,
 }
 
 impl WatchOptions {
     /// Sets key.
     #[inline]
-    fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+    pub(crate) fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
         self.key_range.with_key(key);
         self
     }
@@ -100,9 +195,27 @@ impl WatchOptions {
                 fragment: false,
             },
             key_range: KeyRange::new(),
+            raw_fragments: false,
+            metadata: Metadata::new(),
         }
     }
 
+    /// Requires the member to have a leader. The watch stream then fails with
+    /// [`Error::NoLeader`] once its member loses the leader, instead of stalling,
+    /// so that the watch can be created again through another member.
+    #[inline]
+    pub fn with_require_leader(mut self) -> Self {
+        self.metadata.require_leader();
+        self
+    }
+
+    /// Sets the gRPC metadata `key` to `value` for the watch stream.
+    #[inline]
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
     /// Sets the end of the range [key, end) to watch. If `end` is not given,
     /// only the key argument is watched. If `end` is equal to '\0', all keys greater than
     /// or equal to the key argument are watched.
@@ -177,11 +290,28 @@ impl WatchOptions {
     }
 
     /// Enables splitting large revisions into multiple watch responses.
+    /// The fragments are reassembled by [`WatchStream`] into one response per revision,
+    /// unless [`WatchOptions::with_raw_fragments`] is set.
     #[inline]
     pub const fn with_fragment(mut self) -> Self {
         self.req.fragment = true;
         self
     }
+
+    /// Delivers each fragment of a split revision as a separate `WatchResponse` instead of
+    /// reassembling them. Use [`WatchResponse::fragment`] to tell whether a revision is incomplete.
+    #[inline]
+    pub const fn with_raw_fragments(mut self) -> Self {
+        self.raw_fragments = true;
+        self
+    }
+
+    /// Watches for typed changes, see [`Event::change`].
+    /// It enables `prev_kv` so that updated and deleted changes carry the old key-value pair.
+    #[inline]
+    pub const fn with_changes(self) -> Self {
+        self.with_prev_key()
+    }
 }
 
 impl From<WatchOptions> for WatchCreateRequest {
@@ -240,7 +370,7 @@ pub struct WatchResponse(PbWatchResponse);
 impl WatchResponse {
     /// Creates a new `WatchResponse`.
     #[inline]
-    const fn new(resp: PbWatchResponse) -> Self {
+    pub(crate) const fn new(resp: PbWatchResponse) -> Self {
         Self(resp)
     }
 
@@ -268,7 +398,7 @@ impl WatchResponse {
     /// All events sent to the created watcher will attach with the same watch_id.
     #[allow(dead_code)]
     #[inline]
-    const fn created(&self) -> bool {
+    pub(crate) const fn created(&self) -> bool {
         self.0.created
     }
 
@@ -298,11 +428,25 @@ impl WatchResponse {
         &self.0.cancel_reason
     }
 
+    /// `fragment` is set to true if a large watch response was split over multiple responses,
+    /// and more fragments of the same revision follow.
+    /// It is always false if the fragments are reassembled by [`WatchStream`].
+    #[inline]
+    pub const fn fragment(&self) -> bool {
+        self.0.fragment
+    }
+
     /// Events happened on the watched keys.
     #[inline]
     pub fn events(&self) -> &[Event] {
         unsafe { &*(self.0.events.as_slice() as *const _ as *const [Event]) }
     }
+
+    /// Typed changes of the events happened on the watched keys.
+    #[inline]
+    pub fn changes(&self) -> Vec<EventChange> {
+        self.events().iter().map(Event::change).collect()
+    }
 }
 
 /// Watching event.
@@ -339,6 +483,58 @@ impl Event {
     pub fn prev_kv(&self) -> Option<&KeyValue> {
         self.0.prev_kv.as_ref().map(From::from)
     }
+
+    /// Derives the typed change of the event.
+    /// A `Put` event is a creation if the key's `version` is 1 (its `create_revision` equals
+    /// its `mod_revision`), or an update otherwise.
+    /// The old key-value pair is only available if the watcher is created with
+    /// [`WatchOptions::with_changes`] or [`WatchOptions::with_prev_key`], and the previous
+    /// revision has not been compacted.
+    #[inline]
+    pub fn change(&self) -> EventChange {
+        let kv = KeyValue::new(self.0.kv.clone().unwrap_or_default());
+        let old = self.0.prev_kv.clone().map(KeyValue::new);
+        match self.event_type() {
+            EventType::Put if kv.version() == 1 => EventChange::Created { new: kv },
+            EventType::Put => EventChange::Updated { old, new: kv },
+            EventType::Delete => EventChange::Deleted { old, kv },
+        }
+    }
+}
+
+/// Typed change of a key, derived from a watching [`Event`].
+#[derive(Debug, Clone)]
+pub enum EventChange {
+    /// The key has been created.
+    Created {
+        /// The created key-value pair.
+        new: KeyValue,
+    },
+    /// The value or lease of an existing key has been changed.
+    Updated {
+        /// The key-value pair before the update, if `prev_kv` is enabled.
+        old: Option<KeyValue>,
+        /// The key-value pair after the update.
+        new: KeyValue,
+    },
+    /// The key has been deleted.
+    Deleted {
+        /// The key-value pair before the deletion, if `prev_kv` is enabled.
+        old: Option<KeyValue>,
+        /// The deleted key with its modification revision set to the revision of deletion.
+        kv: KeyValue,
+    },
+}
+
+impl EventChange {
+    /// The key of the changed key-value pair.
+    #[inline]
+    pub fn key(&self) -> &[u8] {
+        match self {
+            EventChange::Created { new } | EventChange::Updated { new, .. } => new.key(),
+            EventChange::Deleted { kv, .. } => kv.key(),
+        }
+    }
 }
 
 /// The watching handle.
@@ -390,37 +586,206 @@ impl Watcher {
 #[cfg_attr(feature = "pub-response-field", visible::StructFields(pub))]
 #[derive(Debug)]
 pub struct WatchStream {
-    stream: Streaming<PbWatchResponse>,
+    stream: WatchMessages,
+    reassemble: bool,
+    fragments: Option<PbWatchResponse>,
+    /// endpoints checked for a leader when the stream loses it
+    pool: Option<Arc<EndpointPool>>,
 }
 
 impl WatchStream {
     /// Creates a new `WatchStream`.
     #[inline]
-    const fn new(stream: Streaming<PbWatchResponse>) -> Self {
-        Self { stream }
+    const fn new(stream: WatchMessages, reassemble: bool, pool: Option<Arc<EndpointPool>>) -> Self {
+        Self {
+            stream,
+            reassemble,
+            fragments: None,
+            pool,
+        }
     }
 
     /// Fetch the next message from this stream.
     #[inline]
     pub async fn message(&mut self) -> Result<Option<WatchResponse>> {
-        match self.stream.message().await? {
-            Some(resp) => Ok(Some(WatchResponse::new(resp))),
-            None => Ok(None),
-        }
+        self.next().await.transpose()
     }
 }
 
 impl Stream for WatchStream {
     type Item = Result<WatchResponse>;
 
-    #[inline]
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.get_mut().stream)
-            .poll_next(cx)
-            .map(|t| match t {
-                Some(Ok(resp)) => Some(Ok(WatchResponse::new(resp))),
-                Some(Err(e)) => Some(Err(From::from(e))),
-                None => None,
-            })
+        let this = self.get_mut();
+        loop {
+            match this.stream.poll_message(cx) {
+                Poll::Ready(Some(Ok(resp))) => {
+                    if !this.reassemble {
+                        return Poll::Ready(Some(Ok(WatchResponse::new(resp))));
+                    }
+                    if let Some(resp) = merge_fragment(&mut this.fragments, resp) {
+                        return Poll::Ready(Some(Ok(WatchResponse::new(resp))));
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    if let (Error::NoLeader(_), Some(pool)) = (&e, &this.pool) {
+                        pool.spawn_leader_check();
+                    }
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Ready(None) => return Poll::Ready(end_fragments(&mut this.fragments)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// The messages of a [`WatchStream`], received over gRPC or the grpc-gateway.
+#[derive(Debug)]
+pub enum WatchMessages {
+    /// A bidirectional gRPC stream.
+    Grpc(Streaming<PbWatchResponse>),
+    /// A watch over the grpc-gateway.
+    #[cfg(feature = "gateway")]
+    Gateway(GatewayWatch),
+}
+
+impl WatchMessages {
+    /// Polls the next message of the stream.
+    #[inline]
+    fn poll_message(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<PbWatchResponse>>> {
+        match self {
+            Self::Grpc(stream) => Pin::new(stream)
+                .poll_next(cx)
+                .map(|resp| resp.map(|resp| resp.map_err(Error::from_stream_status))),
+            #[cfg(feature = "gateway")]
+            Self::Gateway(watch) => watch.poll_message(cx),
+        }
+    }
+}
+
+/// Merges a watch response into the pending fragments of the same revision.
+/// Returns the whole response once its last fragment is received.
+fn merge_fragment(
+    fragments: &mut Option<PbWatchResponse>,
+    mut resp: PbWatchResponse,
+) -> Option<PbWatchResponse> {
+    if let Some(mut pending) = fragments.take() {
+        pending.events.append(&mut resp.events);
+        resp.events = pending.events;
+    }
+
+    if resp.fragment {
+        *fragments = Some(resp);
+        None
+    } else {
+        Some(resp)
+    }
+}
+
+/// Ends a stream, failing once if it ended with a fragmented response incomplete.
+fn end_fragments(fragments: &mut Option<PbWatchResponse>) -> Option<Result<WatchResponse>> {
+    fragments.take().map(|pending| {
+        Err(Error::WatchError(format!(
+            "watch stream ended with {} events of a fragmented response at revision {}",
+            pending.events.len(),
+            pending.header.map(|h| h.revision).unwrap_or_default()
+        )))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::pb::mvccpb::KeyValue as PbKeyValue;
+
+    fn pb_kv(value: &str, create_revision: i64, mod_revision: i64, version: i64) -> PbKeyValue {
+        PbKeyValue {
+            key: b"key".to_vec(),
+            value: value.as_bytes().to_vec(),
+            create_revision,
+            mod_revision,
+            version,
+            lease: 0,
+        }
+    }
+
+    fn event(r#type: EventType, kv: PbKeyValue, prev_kv: Option<PbKeyValue>) -> Event {
+        Event(PbEvent {
+            r#type: r#type as i32,
+            kv: Some(kv),
+            prev_kv,
+        })
+    }
+
+    #[test]
+    fn test_event_change() {
+        let created = event(EventType::Put, pb_kv("1", 2, 2, 1), None);
+        match created.change() {
+            EventChange::Created { new } => assert_eq!(new.value(), b"1"),
+            change => panic!("unexpected change {:?}", change),
+        }
+
+        let updated = event(
+            EventType::Put,
+            pb_kv("2", 2, 3, 2),
+            Some(pb_kv("1", 2, 2, 1)),
+        );
+        match updated.change() {
+            EventChange::Updated { old, new } => {
+                assert_eq!(old.unwrap().value(), b"1");
+                assert_eq!(new.value(), b"2");
+            }
+            change => panic!("unexpected change {:?}", change),
+        }
+
+        let deleted = event(EventType::Delete, pb_kv("", 0, 4, 0), None);
+        match deleted.change() {
+            EventChange::Deleted { old, kv } => {
+                assert!(old.is_none());
+                assert_eq!(kv.key(), b"key");
+                assert_eq!(kv.mod_revision(), 4);
+            }
+            change => panic!("unexpected change {:?}", change),
+        }
+        assert_eq!(deleted.change().key(), b"key");
+    }
+
+    #[test]
+    fn test_merge_fragment() {
+        let response = |value: &str, fragment: bool| PbWatchResponse {
+            watch_id: 1,
+            fragment,
+            events: vec![PbEvent {
+                r#type: EventType::Put as i32,
+                kv: Some(pb_kv(value, 2, 5, 2)),
+                prev_kv: None,
+            }],
+            ..Default::default()
+        };
+
+        let mut fragments = None;
+        assert!(merge_fragment(&mut fragments, response("1", true)).is_none());
+        assert!(merge_fragment(&mut fragments, response("2", true)).is_none());
+        let resp = merge_fragment(&mut fragments, response("3", false)).unwrap();
+        assert!(fragments.is_none());
+        assert!(!resp.fragment);
+        let values: Vec<_> = resp
+            .events
+            .iter()
+            .map(|event| event.kv.as_ref().unwrap().value.clone())
+            .collect();
+        assert_eq!(values, vec![b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]);
+
+        let resp = merge_fragment(&mut fragments, response("4", false)).unwrap();
+        assert_eq!(resp.events.len(), 1);
+        assert!(end_fragments(&mut fragments).is_none());
+
+        assert!(merge_fragment(&mut fragments, response("5", true)).is_none());
+        assert!(matches!(
+            end_fragments(&mut fragments),
+            Some(Err(Error::WatchError(_)))
+        ));
+        assert!(end_fragments(&mut fragments).is_none());
     }
 }